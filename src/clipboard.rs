@@ -0,0 +1,397 @@
+//! Persistent connection for working with the clipboard.
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io;
+use std::os::fd::AsFd;
+
+use os_pipe::{pipe, PipeReader};
+use wayland_client::{ConnectError, Connection, DispatchError, EventQueue};
+
+use crate::common::{self, connect, initialize_with};
+use crate::copy::{self, CopyHandle, MimeSource, PreparedCopy};
+use crate::paste::{self, roundtrip_until, select_mime_type};
+use crate::seat_data::SeatData;
+use crate::utils::{describe_protocol, Protocol};
+
+/// Persistent connection to the Wayland compositor for working with the clipboard.
+///
+/// The free functions in [`copy`] and [`paste`] connect to the compositor anew on every call.
+/// A `Clipboard` connects once and keeps the clipboard manager, the seats and their data devices
+/// around, tracking the seat names and the offered clipboard contents as the events arrive. This
+/// makes it much cheaper to perform many operations in a row.
+///
+/// Copies made through a `Clipboard` share its connection, but are otherwise independent of it:
+/// they keep being served after the `Clipboard` is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # extern crate wl_clipboard_rs;
+/// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Read;
+/// use wl_clipboard_rs::clipboard::Clipboard;
+/// use wl_clipboard_rs::paste::{ClipboardType, MimeType, Seat};
+///
+/// let mut clipboard = Clipboard::new()?;
+///
+/// for _ in 0..10 {
+///     let (mut pipe, _) = clipboard.contents(ClipboardType::Regular,
+///                                            Seat::Unspecified,
+///                                            MimeType::Text)?;
+///
+///     let mut contents = String::new();
+///     pipe.read_to_string(&mut contents)?;
+///     println!("{}", contents);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Clipboard {
+    conn: Connection,
+    queue: EventQueue<paste::State>,
+    state: paste::State,
+}
+
+/// Errors that can occur for connecting to the compositor.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Couldn't open the provided Wayland socket")]
+    SocketOpenError(#[source] io::Error),
+
+    #[error("Couldn't connect to the Wayland compositor")]
+    WaylandConnection(#[source] ConnectError),
+
+    #[error("Wayland compositor communication error")]
+    WaylandCommunication(#[source] DispatchError),
+
+    #[error(
+        "A required Wayland protocol ({}) is not supported by the compositor",
+        describe_protocol(*.protocol, *.version)
+    )]
+    MissingProtocol { protocol: Protocol, version: u32 },
+}
+
+impl From<common::Error> for Error {
+    fn from(x: common::Error) -> Self {
+        use common::Error::*;
+
+        match x {
+            SocketOpenError(err) => Error::SocketOpenError(err),
+            WaylandConnection(err) => Error::WaylandConnection(err),
+            WaylandCommunication(err) => Error::WaylandCommunication(err.into()),
+            MissingProtocol { protocol, version } => Error::MissingProtocol { protocol, version },
+        }
+    }
+}
+
+impl From<Error> for paste::Error {
+    fn from(x: Error) -> Self {
+        match x {
+            Error::SocketOpenError(err) => paste::Error::SocketOpenError(err),
+            Error::WaylandConnection(err) => paste::Error::WaylandConnection(err),
+            Error::WaylandCommunication(err) => paste::Error::WaylandCommunication(err),
+            Error::MissingProtocol { protocol, version } => {
+                paste::Error::MissingProtocol { protocol, version }
+            }
+        }
+    }
+}
+
+impl Clipboard {
+    /// Connects to the Wayland compositor.
+    #[inline]
+    pub fn new() -> Result<Self, Error> {
+        Self::new_internal(None)
+    }
+
+    /// Connects to the Wayland compositor, picking the data-control protocol.
+    ///
+    /// See [`Protocol`].
+    #[inline]
+    pub fn with_protocol(protocol: Protocol) -> Result<Self, Error> {
+        let conn = connect(None)?;
+        Self::from_connection(conn, protocol)
+    }
+
+    /// Connects to the given Wayland display, picking the data-control protocol.
+    ///
+    /// The display is either a display name relative to `XDG_RUNTIME_DIR`, like `wayland-1`, or an
+    /// absolute path to the compositor socket.
+    #[inline]
+    pub fn with_display(display: impl Into<OsString>, protocol: Protocol) -> Result<Self, Error> {
+        let conn = connect(Some(display.into()))?;
+        Self::from_connection(conn, protocol)
+    }
+
+    // The internal function accepts the socket name, used for tests.
+    pub(crate) fn new_internal(socket_name: Option<OsString>) -> Result<Self, Error> {
+        let conn = connect(socket_name)?;
+        Self::from_connection(conn, Protocol::Auto)
+    }
+
+    /// Sets up the clipboard on an existing connection to the Wayland compositor.
+    ///
+    /// The connection can be shared with the rest of the application, the clipboard uses its own
+    /// event queue.
+    ///
+    /// # Examples
+    ///
+    /// Using a socket inherited from the parent process:
+    ///
+    /// ```no_run
+    /// # extern crate wl_clipboard_rs;
+    /// # extern crate wayland_client;
+    /// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::os::fd::FromRawFd;
+    /// use std::os::unix::net::UnixStream;
+    /// use wayland_client::Connection;
+    /// use wl_clipboard_rs::clipboard::Clipboard;
+    /// use wl_clipboard_rs::utils::Protocol;
+    ///
+    /// // SAFETY: the parent process passed us the socket as fd 3.
+    /// let stream = unsafe { UnixStream::from_raw_fd(3) };
+    /// let conn = Connection::from_socket(stream)?;
+    /// let clipboard = Clipboard::from_connection(conn, Protocol::Auto)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_connection(conn: Connection, protocol: Protocol) -> Result<Self, Error> {
+        // Accept any wlr-data-control version, the primary selection support is checked when it's
+        // actually needed.
+        let (mut queue, common) = initialize_with(&conn, protocol, 1..=2)?;
+        let mut state = paste::State::new(common, &queue.handle());
+
+        // Retrieve all seat names and offers.
+        roundtrip_until(&conn, &mut queue, &mut state, None)
+            .map_err(Error::WaylandCommunication)?;
+
+        Ok(Self { conn, queue, state })
+    }
+
+    // Processes the events that arrived since the last operation.
+    fn update(&mut self) -> Result<(), DispatchError> {
+        // The copies share the connection, so the plain EventQueue::roundtrip() won't do.
+        roundtrip_until(&self.conn, &mut self.queue, &mut self.state, None).map(drop)
+    }
+
+    /// Retrieves the offered MIME types.
+    ///
+    /// See [`paste::get_mime_types()`].
+    pub fn mime_types(
+        &mut self,
+        clipboard: paste::ClipboardType,
+        seat: paste::Seat<'_>,
+    ) -> Result<HashSet<String>, paste::Error> {
+        self.update().map_err(paste::Error::WaylandCommunication)?;
+
+        let primary = clipboard == paste::ClipboardType::Primary;
+        let seat = self.state.find_seat(primary, seat)?;
+        let offer = self.state.find_offer(primary, &seat)?;
+        Ok(self.state.offers[&offer].iter().cloned().collect())
+    }
+
+    /// Retrieves the offered MIME types in the order they were offered.
+    ///
+    /// See [`paste::get_mime_types_ordered()`].
+    pub fn mime_types_ordered(
+        &mut self,
+        clipboard: paste::ClipboardType,
+        seat: paste::Seat<'_>,
+    ) -> Result<Vec<String>, paste::Error> {
+        self.update().map_err(paste::Error::WaylandCommunication)?;
+
+        let primary = clipboard == paste::ClipboardType::Primary;
+        let seat = self.state.find_seat(primary, seat)?;
+        let offer = self.state.find_offer(primary, &seat)?;
+        Ok(self.state.offers[&offer].clone())
+    }
+
+    /// Retrieves the clipboard contents.
+    ///
+    /// See [`paste::get_contents()`].
+    pub fn contents(
+        &mut self,
+        clipboard: paste::ClipboardType,
+        seat: paste::Seat<'_>,
+        mime_type: paste::MimeType<'_>,
+    ) -> Result<(PipeReader, String), paste::Error> {
+        self.update().map_err(paste::Error::WaylandCommunication)?;
+
+        let primary = clipboard == paste::ClipboardType::Primary;
+        let seat = self.state.find_seat(primary, seat)?;
+        let offer = self.state.find_offer(primary, &seat)?;
+
+        let Some(mime_type) = select_mime_type(&self.state.offers[&offer], mime_type) else {
+            return Err(paste::Error::NoMimeType);
+        };
+
+        // Create a pipe for content transfer.
+        let (read, write) = pipe().map_err(paste::Error::PipeCreation)?;
+
+        // Start the transfer.
+        offer.receive(mime_type.clone(), write.as_fd());
+        drop(write);
+
+        // Make sure the compositor handles the request before the connection is potentially closed.
+        self.update().map_err(paste::Error::WaylandCommunication)?;
+
+        Ok((read, mime_type))
+    }
+
+    /// Clears the clipboard for the given seat.
+    ///
+    /// See [`copy::clear()`].
+    pub fn clear(
+        &mut self,
+        clipboard: copy::ClipboardType,
+        seat: copy::Seat,
+    ) -> Result<(), copy::Error> {
+        self.update().map_err(copy::Error::WaylandCommunication)?;
+
+        if self.state.common.seats.is_empty() {
+            return Err(copy::Error::NoSeats);
+        }
+
+        let primary = clipboard != copy::ClipboardType::Regular;
+        if primary && !self.state.got_primary_selection {
+            return Err(copy::Error::PrimarySelectionUnsupported);
+        }
+
+        let devices = self
+            .state
+            .common
+            .seats
+            .values()
+            .filter(|data| match &seat {
+                copy::Seat::All => true,
+                copy::Seat::Specific(name) => data.name.as_ref() == Some(name),
+            })
+            .filter_map(|data| data.device.as_ref())
+            .collect::<Vec<_>>();
+
+        if devices.is_empty() {
+            return Err(copy::Error::SeatNotFound);
+        }
+
+        for device in devices {
+            if clipboard != copy::ClipboardType::Regular {
+                device.set_primary_selection(None);
+            }
+            if clipboard != copy::ClipboardType::Primary {
+                device.set_selection(None);
+            }
+        }
+
+        self.update().map_err(copy::Error::WaylandCommunication)
+    }
+
+    // Returns the connection and a copy of the state for preparing a copy that reuses the clipboard
+    // manager, the seats and their data devices.
+    pub(crate) fn shared_state(
+        &mut self,
+        primary: bool,
+    ) -> Result<(Connection, common::State), copy::Error> {
+        self.update().map_err(copy::Error::WaylandCommunication)?;
+
+        if self.state.common.seats.is_empty() {
+            return Err(copy::Error::NoSeats);
+        }
+
+        if primary && !self.state.got_primary_selection {
+            return Err(copy::Error::PrimarySelectionUnsupported);
+        }
+
+        // The offers stay with the clipboard.
+        //
+        // See `common::initialize_with()` on why the seats are fine as keys.
+        #[allow(clippy::mutable_key_type)]
+        let seats = self
+            .state
+            .common
+            .seats
+            .iter()
+            .map(|(seat, data)| {
+                let data = SeatData {
+                    global_name: data.global_name,
+                    name: data.name.clone(),
+                    device: data.device.clone(),
+                    ..Default::default()
+                };
+                (seat.clone(), data)
+            })
+            .collect();
+
+        let common = common::State {
+            seats,
+            clipboard_manager: self.state.common.clipboard_manager.clone(),
+        };
+        Ok((self.conn.clone(), common))
+    }
+
+    /// Copies data to the clipboard.
+    ///
+    /// See [`copy::copy()`]. The copy uses the clipboard manager and the data devices of this
+    /// `Clipboard`, so the protocol and the display in `options` are ignored, and unlike with
+    /// [`copy::copy()`], the seats that appear while the copy is being served are not copied to.
+    #[inline]
+    pub fn copy(
+        &mut self,
+        options: copy::Options,
+        source: copy::Source,
+        mime_type: copy::MimeType,
+    ) -> Result<CopyHandle, copy::Error> {
+        let sources = vec![MimeSource { source, mime_type }];
+        self.copy_multi(options, sources)
+    }
+
+    /// Copies data to the clipboard, offering multiple data sources.
+    ///
+    /// See [`copy::copy_multi()`] and [`copy()`](Self::copy).
+    #[inline]
+    pub fn copy_multi(
+        &mut self,
+        options: copy::Options,
+        sources: Vec<MimeSource>,
+    ) -> Result<CopyHandle, copy::Error> {
+        let foreground = options.foreground;
+        let prepared_copy = copy::prepare_copy_shared(self, options, sources)?;
+        copy::serve_copy(foreground, move || Ok(prepared_copy))
+    }
+
+    /// Prepares a data copy to the clipboard.
+    ///
+    /// See [`copy::prepare_copy()`] and [`copy()`](Self::copy).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `foreground` is `false`.
+    #[inline]
+    pub fn prepare_copy(
+        &mut self,
+        options: copy::Options,
+        source: copy::Source,
+        mime_type: copy::MimeType,
+    ) -> Result<PreparedCopy, copy::Error> {
+        let sources = vec![MimeSource { source, mime_type }];
+        self.prepare_copy_multi(options, sources)
+    }
+
+    /// Prepares a data copy to the clipboard, offering multiple data sources.
+    ///
+    /// See [`copy::prepare_copy_multi()`] and [`copy()`](Self::copy).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `foreground` is `false`.
+    #[inline]
+    pub fn prepare_copy_multi(
+        &mut self,
+        options: copy::Options,
+        sources: Vec<MimeSource>,
+    ) -> Result<PreparedCopy, copy::Error> {
+        assert!(options.foreground);
+
+        copy::prepare_copy_shared(self, options, sources)
+    }
+}
@@ -1,19 +1,22 @@
 use std::collections::HashMap;
 use std::ffi::OsString;
+use std::ops::RangeInclusive;
 use std::os::unix::net::UnixStream;
 use std::path::PathBuf;
+use std::time::Instant;
 use std::{env, io};
 
-use wayland_backend::client::WaylandError;
-use wayland_client::globals::{registry_queue_init, GlobalError, GlobalListContents};
+use wayland_backend::client::{ReadEventsGuard, WaylandError};
+use wayland_client::globals::{registry_queue_init, Global, GlobalError, GlobalListContents};
 use wayland_client::protocol::wl_registry::WlRegistry;
 use wayland_client::protocol::wl_seat::{self, WlSeat};
-use wayland_client::{ConnectError, Connection, Dispatch, EventQueue, Proxy};
+use wayland_client::{ConnectError, Connection, Dispatch, EventQueue, Proxy, QueueHandle};
 use wayland_protocols::ext::data_control::v1::client::ext_data_control_manager_v1::ExtDataControlManagerV1;
 use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
 
 use crate::data_control::Manager;
 use crate::seat_data::SeatData;
+use crate::utils::{describe_protocol, Protocol};
 
 pub struct State {
     pub seats: HashMap<WlSeat, SeatData>,
@@ -21,6 +24,7 @@ pub struct State {
 }
 
 #[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Couldn't open the provided Wayland socket")]
     SocketOpenError(#[source] io::Error),
@@ -32,10 +36,10 @@ pub enum Error {
     WaylandCommunication(#[source] WaylandError),
 
     #[error(
-        "A required Wayland protocol (ext-data-control, or wlr-data-control version {version}) \
-         is not supported by the compositor"
+        "A required Wayland protocol ({}) is not supported by the compositor",
+        describe_protocol(*.protocol, *.version)
     )]
-    MissingProtocol { version: u32 },
+    MissingProtocol { protocol: Protocol, version: u32 },
 }
 
 impl<S> Dispatch<WlSeat, (), S> for State
@@ -53,14 +57,91 @@ where
         let state = parent.as_mut();
 
         if let wl_seat::Event::Name { name } = event {
-            state.seats.get_mut(seat).unwrap().set_name(name);
+            // The seat may have been removed already.
+            if let Some(data) = state.seats.get_mut(seat) {
+                data.set_name(name);
+            }
         }
     }
 }
 
-pub fn initialize<S>(
-    primary: bool,
-    socket_name: Option<OsString>,
+/// Releases the seat, if its version supports that.
+pub fn release_seat(seat: &WlSeat) {
+    if seat.version() >= 5 {
+        seat.release();
+    }
+}
+
+/// Returns the wlr-data-control versions needed for working with the given clipboard.
+pub fn wlr_versions(primary: bool) -> RangeInclusive<u32> {
+    let version = if primary { 2 } else { 1 };
+    version..=version
+}
+
+/// Reads the events from the connection, if there are any.
+pub fn read_events(guard: ReadEventsGuard) -> Result<(), WaylandError> {
+    match guard.read() {
+        Err(WaylandError::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+        result => result.map(drop),
+    }
+}
+
+/// Returns the `poll()` timeout in milliseconds for waiting until `deadline`.
+pub fn poll_timeout(deadline: Option<Instant>) -> i32 {
+    deadline.map_or(-1, |deadline| {
+        // Round up so as to not wake up right before the deadline.
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        remaining
+            .as_nanos()
+            .div_ceil(1_000_000)
+            .try_into()
+            .unwrap_or(i32::MAX)
+    })
+}
+
+/// Returns the path of the Wayland socket.
+///
+/// `name` is either a display name relative to `XDG_RUNTIME_DIR`, or an absolute socket path.
+pub fn socket_path(name: OsString) -> Result<PathBuf, ConnectError> {
+    let name = PathBuf::from(name);
+    if name.is_absolute() {
+        return Ok(name);
+    }
+
+    let mut socket_path = env::var_os("XDG_RUNTIME_DIR")
+        .map(Into::<PathBuf>::into)
+        .ok_or(ConnectError::NoCompositor)?;
+    if !socket_path.is_absolute() {
+        return Err(ConnectError::NoCompositor);
+    }
+    socket_path.push(name);
+
+    Ok(socket_path)
+}
+
+/// Connects to the Wayland compositor.
+///
+/// `display` is passed to [`socket_path()`], if it's `None`, the environment is used.
+pub fn connect(display: Option<OsString>) -> Result<Connection, Error> {
+    match display {
+        Some(name) => {
+            let socket_path = socket_path(name).map_err(Error::WaylandConnection)?;
+            let stream = UnixStream::connect(socket_path).map_err(Error::SocketOpenError)?;
+            Connection::from_socket(stream)
+        }
+        None => Connection::connect_to_env(),
+    }
+    .map_err(Error::WaylandConnection)
+}
+
+/// Binds the globals on a new event queue of the connection.
+///
+/// `protocol` picks between ext-data-control and wlr-data-control. `wlr_versions` is the range of
+/// acceptable wlr-data-control versions.
+pub fn initialize_with<S>(
+    conn: &Connection,
+    protocol: Protocol,
+    wlr_versions: RangeInclusive<u32>,
 ) -> Result<(EventQueue<S>, State), Error>
 where
     S: Dispatch<WlRegistry, GlobalListContents> + 'static,
@@ -69,63 +150,97 @@ where
     S: Dispatch<WlSeat, ()>,
     S: AsMut<State>,
 {
-    // Connect to the Wayland compositor.
-    let conn = match socket_name {
-        Some(name) => {
-            let mut socket_path = env::var_os("XDG_RUNTIME_DIR")
-                .map(Into::<PathBuf>::into)
-                .ok_or(ConnectError::NoCompositor)
-                .map_err(Error::WaylandConnection)?;
-            if !socket_path.is_absolute() {
-                return Err(Error::WaylandConnection(ConnectError::NoCompositor));
-            }
-            socket_path.push(name);
-
-            let stream = UnixStream::connect(socket_path).map_err(Error::SocketOpenError)?;
-            Connection::from_socket(stream)
-        }
-        None => Connection::connect_to_env(),
-    }
-    .map_err(Error::WaylandConnection)?;
-
     // Retrieve the global interfaces.
     let (globals, queue) =
-        registry_queue_init::<S>(&conn).map_err(|err| match err {
+        registry_queue_init::<S>(conn).map_err(|err| match err {
                                            GlobalError::Backend(err) => Error::WaylandCommunication(err),
                                            GlobalError::InvalidId(err) => panic!("How's this possible? \
                                                                                   Is there no wl_registry? \
                                                                                   {:?}",
                                                                                  err),
                                        })?;
-    let qh = &queue.handle();
+    let state = globals.contents().with_list(|list| {
+        bind_globals(
+            globals.registry(),
+            list,
+            &queue.handle(),
+            protocol,
+            wlr_versions,
+        )
+    })?;
 
-    // Verify that we got the clipboard manager.
-    let ext_manager = globals.bind(qh, 1..=1, ()).ok().map(Manager::Ext);
+    Ok((queue, state))
+}
+
+/// Binds the clipboard manager and the seats among `globals` on the event queue of `qh`.
+///
+/// See [`initialize_with()`] for `protocol` and `wlr_versions`.
+pub fn bind_globals<S>(
+    registry: &WlRegistry,
+    globals: &[Global],
+    qh: &QueueHandle<S>,
+    protocol: Protocol,
+    wlr_versions: RangeInclusive<u32>,
+) -> Result<State, Error>
+where
+    S: Dispatch<ZwlrDataControlManagerV1, ()> + 'static,
+    S: Dispatch<ExtDataControlManagerV1, ()>,
+    S: Dispatch<WlSeat, ()>,
+{
+    // Finds the global and the version to bind it at.
+    let find = |interface: &str, versions: &RangeInclusive<u32>| {
+        globals
+            .iter()
+            .find(|global| global.interface == interface && global.version >= *versions.start())
+            .map(|global| (global.name, global.version.min(*versions.end())))
+    };
 
-    let wlr_v = if primary { 2 } else { 1 };
-    let wlr_manager = || globals.bind(qh, wlr_v..=wlr_v, ()).ok().map(Manager::Zwlr);
+    // Verify that we got the clipboard manager.
+    let wlr_v = *wlr_versions.start();
+    let ext_manager = || {
+        find(ExtDataControlManagerV1::interface().name, &(1..=1))
+            .map(|(name, version)| Manager::Ext(registry.bind(name, version, qh, ())))
+    };
+    let wlr_manager = || {
+        find(ZwlrDataControlManagerV1::interface().name, &wlr_versions)
+            .map(|(name, version)| Manager::Zwlr(registry.bind(name, version, qh, ())))
+    };
 
-    let clipboard_manager = match ext_manager.or_else(wlr_manager) {
-        Some(manager) => manager,
-        None => return Err(Error::MissingProtocol { version: wlr_v }),
+    let clipboard_manager = match protocol {
+        Protocol::Auto => ext_manager().or_else(wlr_manager),
+        Protocol::ExtOnly => ext_manager(),
+        Protocol::WlrOnly => wlr_manager(),
+        Protocol::PreferWlr => wlr_manager().or_else(ext_manager),
     };
 
-    let registry = globals.registry();
-    let seats = globals.contents().with_list(|globals| {
-        globals
-            .iter()
-            .filter(|global| global.interface == WlSeat::interface().name && global.version >= 2)
-            .map(|global| {
-                let seat = registry.bind(global.name, 2, qh, ());
-                (seat, SeatData::default())
+    let clipboard_manager = match clipboard_manager {
+        Some(manager) => manager,
+        None => {
+            return Err(Error::MissingProtocol {
+                protocol,
+                version: wlr_v,
             })
-            .collect()
-    });
+        }
+    };
 
-    let state = State {
+    // The proxies are hashed by their ID, which doesn't change, even though the native_lib
+    // backend gives them interior mutability.
+    #[allow(clippy::mutable_key_type)]
+    let seats = globals
+        .iter()
+        .filter(|global| global.interface == WlSeat::interface().name && global.version >= 2)
+        .map(|global| {
+            let seat = registry.bind(global.name, global.version.min(5), qh, ());
+            let data = SeatData {
+                global_name: global.name,
+                ..Default::default()
+            };
+            (seat, data)
+        })
+        .collect();
+
+    Ok(State {
         seats,
         clipboard_manager,
-    };
-
-    Ok((queue, state))
+    })
 }
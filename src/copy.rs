@@ -4,26 +4,42 @@ use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
 use std::fs::{remove_dir, remove_file, File, OpenOptions};
+use std::hash::{Hash, Hasher};
 use std::io::{self, Read, Seek, SeekFrom, Write};
-use std::path::PathBuf;
-use std::sync::mpsc::sync_channel;
-use std::{iter, thread};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use std::{cmp, fmt, iter, mem, panic, thread};
 
 use log::trace;
+use os_pipe::{pipe, PipeReader, PipeWriter};
+use rustix::event::{poll, PollFd, PollFlags};
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+use rustix::fs::{fcntl_add_seals, memfd_create, MemfdFlags, SealFlags};
 use rustix::fs::{fcntl_setfl, OFlags};
+use rustix::io::Errno;
+use wayland_backend::client::{ReadEventsGuard, WaylandError};
 use wayland_client::globals::GlobalListContents;
-use wayland_client::protocol::wl_registry::WlRegistry;
+use wayland_client::protocol::wl_callback::{self, WlCallback};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
 use wayland_client::protocol::wl_seat::WlSeat;
 use wayland_client::{
-    delegate_dispatch, event_created_child, ConnectError, Dispatch, DispatchError, EventQueue,
+    delegate_dispatch, event_created_child, ConnectError, Connection, Dispatch, DispatchError,
+    EventQueue, Proxy, QueueHandle,
 };
 
-use crate::common::{self, initialize};
+use crate::clipboard::Clipboard;
+use crate::common::{self, connect, initialize_with, poll_timeout, release_seat, wlr_versions};
 use crate::data_control::{
     self, impl_dispatch_device, impl_dispatch_manager, impl_dispatch_offer, impl_dispatch_source,
 };
+use crate::paste;
 use crate::seat_data::SeatData;
-use crate::utils::is_text;
+use crate::utils::{describe_protocol, is_text, Protocol};
 
 /// The clipboard to operate on.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord, Default)]
@@ -58,7 +74,10 @@ pub enum MimeType {
 }
 
 /// Source for copying.
-#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+///
+/// Sources holding a reader or a provider compare and hash by the identity of the shared object
+/// rather than by the data it produces.
+#[derive(Clone)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 pub enum Source {
     /// Copy contents of the standard input.
@@ -66,6 +85,118 @@ pub enum Source {
     StdIn,
     /// Copy the given bytes.
     Bytes(Box<[u8]>),
+    /// Copy contents of the given reader.
+    ///
+    /// The reader is drained into the backing storage when the copy is prepared, so it can be
+    /// e.g. a decompressor, a socket or the standard output of a child process. Clones of the
+    /// source share the same reader.
+    #[cfg_attr(test, proptest(skip))]
+    Reader(Arc<Mutex<dyn Read + Send>>),
+    /// Copy contents of the file at the given path.
+    ///
+    /// When autodetecting the MIME type, the file extension is used as a hint first.
+    ///
+    /// If `snapshot` is `false`, the file is served directly instead of being copied, and every
+    /// paste request reads it anew, so later changes to the file show up in pastes. Newline
+    /// trimming is not applied in this case. If `snapshot` is `true`, the file contents are
+    /// copied when the copy is prepared, like for any other source.
+    #[cfg_attr(test, proptest(skip))]
+    File { path: PathBuf, snapshot: bool },
+    /// Generate the data on demand with the given provider.
+    ///
+    /// The provider is only invoked when a client actually pastes, once per paste request, so
+    /// offering expensive formats costs almost nothing until they are requested. The MIME type
+    /// can't be autodetected for this source, and newline trimming is not applied.
+    #[cfg_attr(test, proptest(skip))]
+    Provider(Arc<dyn DataProvider>),
+}
+
+/// Provider of data generated on demand.
+///
+/// Used for [`Source::Provider`]. It's implemented for closures with a matching signature.
+pub trait DataProvider: Send + Sync {
+    /// Writes the data for the requested MIME type into `target`.
+    ///
+    /// `mime_type` is the MIME type requested by the pasting client. When text MIME types are
+    /// offered, this may be one of the additional plain text MIME types rather than the one the
+    /// provider was registered with.
+    fn provide(&self, mime_type: &str, target: &mut dyn Write) -> io::Result<()>;
+}
+
+impl<F> DataProvider for F
+where
+    F: Fn(&str, &mut dyn Write) -> io::Result<()> + Send + Sync,
+{
+    fn provide(&self, mime_type: &str, target: &mut dyn Write) -> io::Result<()> {
+        self(mime_type, target)
+    }
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::StdIn => f.write_str("StdIn"),
+            Source::Bytes(data) => f.debug_tuple("Bytes").field(data).finish(),
+            Source::Reader(_) => f.debug_tuple("Reader").finish_non_exhaustive(),
+            Source::File { path, snapshot } => f
+                .debug_struct("File")
+                .field("path", path)
+                .field("snapshot", snapshot)
+                .finish(),
+            Source::Provider(_) => f.debug_tuple("Provider").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Key used to compare and hash sources.
+#[derive(Eq, PartialEq, Hash, PartialOrd, Ord)]
+enum SourceKey<'a> {
+    StdIn,
+    Bytes(&'a [u8]),
+    Reader(*const ()),
+    File { path: &'a Path, snapshot: bool },
+    Provider(*const ()),
+}
+
+impl Source {
+    fn key(&self) -> SourceKey<'_> {
+        match self {
+            Source::StdIn => SourceKey::StdIn,
+            Source::Bytes(data) => SourceKey::Bytes(data),
+            Source::Reader(reader) => SourceKey::Reader(Arc::as_ptr(reader).cast()),
+            Source::File { path, snapshot } => SourceKey::File {
+                path,
+                snapshot: *snapshot,
+            },
+            Source::Provider(provider) => SourceKey::Provider(Arc::as_ptr(provider).cast()),
+        }
+    }
+}
+
+impl PartialEq for Source {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Source {}
+
+impl Hash for Source {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialOrd for Source {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Source {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
 }
 
 /// Source for copying, with a MIME type.
@@ -80,6 +211,9 @@ pub struct MimeSource {
 }
 
 /// Seat to operate on.
+///
+/// When copying, the seats that appear while the copy is being served are copied to as well if
+/// they match, and the seats that disappear are no longer served.
 #[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord, Default)]
 pub enum Seat {
     /// Operate on all existing seats at once.
@@ -99,6 +233,71 @@ pub enum ServeRequests {
     Only(usize),
 }
 
+/// Paste request made by some client.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct PasteRequest {
+    /// The requested MIME type.
+    pub mime_type: String,
+    /// Name of the seat the request came through, if the compositor advertised one.
+    pub seat: Option<String>,
+    /// The clipboard the request was for, either `Regular` or `Primary`.
+    pub clipboard: ClipboardType,
+}
+
+/// Event that occurred while serving a copy.
+///
+/// Use [`PreparedCopy::set_event_callback()`] or [`CopyHandle::events()`] to receive them.
+#[derive(Clone, Debug)]
+pub enum CopyEvent {
+    /// A paste request was served.
+    Served {
+        request: PasteRequest,
+        /// Number of bytes written to the pasting client.
+        bytes: u64,
+        /// Time it took to serve the request.
+        duration: Duration,
+    },
+    /// Serving a paste request failed.
+    ///
+    /// Serving stops after such an error, and the error itself is returned once serving finishes.
+    Failed {
+        request: PasteRequest,
+        /// The error that occurred.
+        error: Arc<DataSourceError>,
+        /// Time spent on the request before it failed.
+        duration: Duration,
+    },
+    /// The copy expired and stopped being served.
+    ///
+    /// See [`Options::expire_after()`].
+    Expired,
+    /// The offer was cancelled, usually because some other client set the selection.
+    Cancelled {
+        /// Name of the seat, if the compositor advertised one.
+        seat: Option<String>,
+        /// The clipboard that was taken over, either `Regular` or `Primary`.
+        clipboard: ClipboardType,
+    },
+}
+
+/// Storage for the copied data.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum Storage {
+    /// Keep the data in memory if possible, otherwise fall back to temporary files.
+    #[default]
+    Auto,
+    /// Keep the data in memory, in sealed `memfd`s.
+    ///
+    /// The data never touches the disk, and it's freed automatically when the process exits.
+    Memory,
+    /// Keep the data in temporary files.
+    ///
+    /// The files are removed once the copy is done, but they may be left behind if the process
+    /// is killed.
+    TempFile,
+}
+
 /// Options and flags that are used to customize the copying.
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash, PartialOrd, Ord)]
 pub struct Options {
@@ -117,7 +316,7 @@ pub struct Options {
     ///
     /// Setting this flag will result in the call to `copy()` **blocking** until all data sources
     /// it creates are destroyed, e.g. until someone else copies something into the clipboard.
-    foreground: bool,
+    pub(crate) foreground: bool,
 
     /// Number of paste requests to serve.
     ///
@@ -132,6 +331,30 @@ pub struct Options {
     /// Omits additionally offered `text/plain;charset=utf-8`, `text/plain`, `STRING`, `UTF8_STRING` and
     /// `TEXT` mime types which are offered by default if at least one text mime type is provided.
     omit_additional_text_mime_types: bool,
+
+    /// Storage for the copied data.
+    storage: Storage,
+
+    /// Time after which to stop serving copy requests.
+    expire_after: Option<Duration>,
+
+    /// Clear the clipboard upon expiry if it still holds the copied data.
+    clear_on_expiry: bool,
+
+    /// Restore the previous clipboard contents once done serving.
+    restore_previous: bool,
+
+    /// Time limit for reading the previous clipboard contents.
+    snapshot_timeout: Option<Duration>,
+
+    /// Maximum size of the previous clipboard contents in bytes, for each MIME type.
+    snapshot_max_size: Option<u64>,
+
+    /// Data-control protocol to use.
+    protocol: Protocol,
+
+    /// Wayland display to connect to, `None` means the one from the environment.
+    display: Option<OsString>,
 }
 
 /// A copy operation ready to start serving requests.
@@ -139,11 +362,109 @@ pub struct PreparedCopy {
     queue: EventQueue<State>,
     state: State,
     sources: Vec<data_control::Source>,
+    expiry: Option<Instant>,
+    clear_on_expiry: bool,
+    restore_previous: bool,
+}
+
+/// Handle to a copy operation.
+///
+/// Returned by [`copy()`] and [`copy_multi()`]. In the background mode, it can be used to stop
+/// serving and to wait for the serving to finish. Dropping the handle does not stop serving.
+///
+/// In the foreground mode, the serving is already finished by the time the handle is returned.
+pub struct CopyHandle {
+    thread: Option<JoinHandle<Result<(), Error>>>,
+    cancel: Option<Arc<CancelToken>>,
+    events: Option<Receiver<CopyEvent>>,
+}
+
+// Signals the serving thread to stop.
+//
+// Both ends of the pipe are kept alive for as long as either side holds the token, so that
+// neither the write nor the poll can fail because the other side went away.
+struct CancelToken {
+    cancelled: AtomicBool,
+    read: PipeReader,
+    write: PipeWriter,
+}
+
+impl CancelToken {
+    fn new() -> io::Result<Self> {
+        let (read, write) = pipe()?;
+        Ok(Self {
+            cancelled: AtomicBool::new(false),
+            read,
+            write,
+        })
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+
+        // Wake up the serving thread.
+        let _ = (&self.write).write(&[0]);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
 }
 
-/// Errors that can occur for copying the source data to a temporary file.
+impl CopyHandle {
+    /// Stops serving the copy.
+    ///
+    /// The data sources are destroyed, withdrawing the offer from the clipboard, and the
+    /// temporary files are removed. Use [`join()`](Self::join) to wait until this is done.
+    pub fn cancel(&self) {
+        if let Some(cancel) = &self.cancel {
+            cancel.cancel();
+        }
+    }
+
+    /// Returns `true` if the copy is still being served.
+    pub fn is_serving(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Returns the receiver of the events that occur while serving the copy.
+    ///
+    /// The events are buffered until received. The sender is dropped once serving finishes, so
+    /// iterating over the receiver ends at that point.
+    ///
+    /// Returns `None` in the foreground mode.
+    pub fn events(&self) -> Option<&Receiver<CopyEvent>> {
+        self.events.as_ref()
+    }
+
+    /// Waits for the serving to finish and returns its result.
+    pub fn join(self) -> Result<(), Error> {
+        match self.thread {
+            Some(thread) => thread
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Errors that can occur for copying the source data to the backing storage.
 #[derive(thiserror::Error, Debug)]
 pub enum SourceCreationError {
+    #[error("Couldn't open the source file")]
+    FileOpen(#[source] io::Error),
+
+    #[error("The MIME type of a data provider can't be autodetected")]
+    ProviderAutodetect,
+
+    #[error("Couldn't create an in-memory file")]
+    MemfdCreate(#[source] io::Error),
+
+    #[error("Couldn't seal the in-memory file")]
+    MemfdSeal(#[source] io::Error),
+
     #[error("Couldn't create a temporary directory")]
     TempDirCreate(#[source] io::Error),
 
@@ -188,10 +509,10 @@ pub enum Error {
     WaylandCommunication(#[source] DispatchError),
 
     #[error(
-        "A required Wayland protocol (ext-data-control, or wlr-data-control version {version}) \
-         is not supported by the compositor"
+        "A required Wayland protocol ({}) is not supported by the compositor",
+        describe_protocol(*.protocol, *.version)
     )]
-    MissingProtocol { version: u32 },
+    MissingProtocol { protocol: Protocol, version: u32 },
 
     #[error("The compositor does not support primary selection")]
     PrimarySelectionUnsupported,
@@ -210,6 +531,15 @@ pub enum Error {
 
     #[error("Error satisfying a paste request")]
     Paste(#[source] DataSourceError),
+
+    #[error("Couldn't register the Wayland connection with the async reactor")]
+    AsyncRegistration(#[source] io::Error),
+
+    #[error("Couldn't create a pipe for cancelling the copy")]
+    PipeCreation(#[source] io::Error),
+
+    #[error("Couldn't read the current clipboard contents to restore them later")]
+    Snapshot(#[source] paste::Error),
 }
 
 impl From<common::Error> for Error {
@@ -220,7 +550,7 @@ impl From<common::Error> for Error {
             SocketOpenError(err) => Error::SocketOpenError(err),
             WaylandConnection(err) => Error::WaylandConnection(err),
             WaylandCommunication(err) => Error::WaylandCommunication(err.into()),
-            MissingProtocol { version } => Error::MissingProtocol { version },
+            MissingProtocol { protocol, version } => Error::MissingProtocol { protocol, version },
         }
     }
 }
@@ -232,6 +562,101 @@ pub enum DataSourceError {
 
     #[error("Couldn't copy the data to the target file descriptor")]
     Copy(#[source] io::Error),
+
+    #[error("The data provider failed")]
+    Provider(#[source] io::Error),
+}
+
+impl DataSourceError {
+    // io::Error isn't Clone, so the copy reported in CopyEvent::Failed keeps the kind, the OS
+    // error code and the message of the original one.
+    fn duplicate(&self) -> Self {
+        let duplicate = |err: &io::Error| match err.raw_os_error() {
+            Some(code) => io::Error::from_raw_os_error(code),
+            None => io::Error::new(err.kind(), err.to_string()),
+        };
+
+        match self {
+            DataSourceError::FileOpen(err) => DataSourceError::FileOpen(duplicate(err)),
+            DataSourceError::Copy(err) => DataSourceError::Copy(duplicate(err)),
+            DataSourceError::Provider(err) => DataSourceError::Provider(duplicate(err)),
+        }
+    }
+}
+
+/// Storage backing the data offered under one or more MIME types.
+#[derive(Clone)]
+pub(crate) enum Data {
+    /// A temporary file, removed along with its directory once we're done.
+    TempFile(PathBuf),
+    /// A file provided by the user, served as is.
+    File(PathBuf),
+    /// A sealed memfd.
+    Memory(Arc<File>),
+    /// Data generated on demand.
+    Provider(Arc<dyn DataProvider>),
+}
+
+impl Data {
+    // Returns the number of bytes written.
+    fn write_to(&self, mime_type: &str, target: &mut File) -> Result<u64, DataSourceError> {
+        match self {
+            Data::TempFile(path) | Data::File(path) => {
+                let mut data_file = File::open(path).map_err(DataSourceError::FileOpen)?;
+                io::copy(&mut data_file, target).map_err(DataSourceError::Copy)
+            }
+            Data::Memory(file) => {
+                // Requests are served one at a time, so sharing the file offset is fine.
+                let mut data_file = &**file;
+                data_file
+                    .seek(SeekFrom::Start(0))
+                    .map_err(DataSourceError::Copy)?;
+                io::copy(&mut data_file, target).map_err(DataSourceError::Copy)
+            }
+            Data::Provider(provider) => {
+                let mut target = CountingWriter {
+                    inner: target,
+                    count: 0,
+                };
+                provider
+                    .provide(mime_type, &mut target)
+                    .map_err(DataSourceError::Provider)?;
+                Ok(target.count)
+            }
+        }
+    }
+}
+
+// Counts the bytes written by a data provider.
+struct CountingWriter<'a> {
+    inner: &'a mut File,
+    count: u64,
+}
+
+impl Write for CountingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Where a data source is offered, and what happens to it.
+struct SourceInfo {
+    seat: Option<String>,
+    device: data_control::Device,
+    clipboard: ClipboardType,
+    // Set once some other client takes over the selection.
+    cancelled: bool,
+    // The clipboard contents from before the copy, to restore afterwards. `None` if the clipboard
+    // was empty or isn't going to be restored.
+    previous: Option<HashMap<String, Data>>,
+    // The data served by a source that restores the previous clipboard contents.
+    restored: Option<HashMap<String, Data>>,
 }
 
 struct State {
@@ -240,10 +665,178 @@ struct State {
     // This bool can be set to true when serving a request: either if an error occurs, or if the
     // number of requests to serve was limited and the last request was served.
     should_quit: bool,
-    data_paths: HashMap<String, PathBuf>,
+    data: HashMap<String, Data>,
     serve_requests: ServeRequests,
     // An error that occurred while serving a request, if any.
     error: Option<DataSourceError>,
+    sources: HashMap<data_control::Source, SourceInfo>,
+    event_callback: Option<Box<dyn FnMut(CopyEvent) + Send>>,
+    // The clipboard and the seats to copy to, for the seats that appear while serving.
+    clipboard: ClipboardType,
+    seat: Seat,
+    // Whether the seats that appear while serving get the copied data too.
+    offer_to_new_seats: bool,
+    // The data sources created for the seats that appeared while serving, to be picked up by
+    // PreparedCopy.
+    new_sources: Vec<data_control::Source>,
+}
+
+impl State {
+    fn new(common: common::State) -> Self {
+        Self {
+            common,
+            got_primary_selection: false,
+            should_quit: false,
+            data: HashMap::new(),
+            serve_requests: ServeRequests::default(),
+            error: None,
+            sources: HashMap::new(),
+            event_callback: None,
+            clipboard: ClipboardType::default(),
+            seat: Seat::default(),
+            offer_to_new_seats: false,
+            new_sources: Vec::new(),
+        }
+    }
+
+    // Returns the data devices of the seats to copy to.
+    fn devices(&self, seat: &Seat) -> Result<Vec<SeatDevice>, Error> {
+        let devices = self
+            .common
+            .seats
+            .values()
+            .filter(|data| match seat {
+                // If no seat was specified, handle all of them.
+                Seat::All => true,
+                Seat::Specific(desired_name) => data.name.as_ref() == Some(desired_name),
+            })
+            .filter_map(|data| {
+                let device = data.device.clone()?;
+                Some((data.name.clone(), device))
+            })
+            .collect::<Vec<_>>();
+
+        // If we didn't find the seat, print an error message and exit.
+        //
+        // This also triggers when we found the seat but it had no data device; is this what we
+        // want?
+        if devices.is_empty() {
+            return Err(Error::SeatNotFound);
+        }
+
+        Ok(devices)
+    }
+
+    fn emit(&mut self, event: CopyEvent) {
+        if let Some(callback) = &mut self.event_callback {
+            callback(event);
+        }
+    }
+
+    fn paste_request(&self, source: &data_control::Source, mime_type: String) -> PasteRequest {
+        let info = &self.sources[source];
+        PasteRequest {
+            mime_type,
+            seat: info.seat.clone(),
+            clipboard: info.clipboard,
+        }
+    }
+
+    // Creates a data source with the copied data and sets it as the selection of the device.
+    fn offer(
+        &mut self,
+        qh: &QueueHandle<Self>,
+        seat: Option<String>,
+        device: data_control::Device,
+        primary: bool,
+        previous: Option<HashMap<String, Data>>,
+    ) -> data_control::Source {
+        let data_source = self.common.clipboard_manager.create_data_source(qh);
+
+        for mime_type in self.data.keys() {
+            data_source.offer(mime_type.clone());
+        }
+
+        if primary {
+            device.set_primary_selection(Some(&data_source));
+        } else {
+            device.set_selection(Some(&data_source));
+        }
+
+        let info = SourceInfo {
+            seat,
+            device,
+            clipboard: if primary {
+                ClipboardType::Primary
+            } else {
+                ClipboardType::Regular
+            },
+            cancelled: false,
+            previous,
+            restored: None,
+        };
+        self.sources.insert(data_source.clone(), info);
+
+        // If we need to serve 0 requests, kill the data source right away.
+        if let ServeRequests::Only(0) = self.serve_requests {
+            data_source.destroy();
+        }
+        data_source
+    }
+
+    // Copies to a seat that appeared while serving, if it's one of the seats to copy to.
+    //
+    // Releases the seat otherwise.
+    fn offer_to_new_seat(&mut self, seat: &WlSeat, qh: &QueueHandle<Self>) {
+        // The seat may have been removed already.
+        let Some(data) = self.common.seats.get_mut(seat) else {
+            return;
+        };
+
+        let wanted = match &self.seat {
+            Seat::All => true,
+            Seat::Specific(desired_name) => data.name.as_ref() == Some(desired_name),
+        };
+        if !wanted || !self.offer_to_new_seats || self.should_quit {
+            self.common.seats.remove(seat);
+            release_seat(seat);
+            return;
+        }
+
+        let device = self
+            .common
+            .clipboard_manager
+            .get_data_device(seat, qh, seat.clone());
+        data.set_device(Some(device.clone()));
+
+        let name = data.name.clone();
+        let primaries: &[bool] = match self.clipboard {
+            ClipboardType::Regular => &[false],
+            ClipboardType::Primary => &[true],
+            ClipboardType::Both => &[false, true],
+        };
+        for &primary in primaries {
+            // The seat is new, so its clipboard was empty before the copy.
+            let data_source = self.offer(qh, name.clone(), device.clone(), primary, None);
+            self.new_sources.push(data_source);
+        }
+    }
+
+    // Stops offering the copied data on a data device that is gone.
+    fn withdraw(&mut self, device: &data_control::Device) {
+        for (source, info) in &mut self.sources {
+            if info.device != *device {
+                continue;
+            }
+
+            if source.is_alive() {
+                source.destroy();
+            }
+
+            // There's no selection left to restore either.
+            info.cancelled = true;
+        }
+    }
 }
 
 delegate_dispatch!(State: [WlSeat: ()] => common::State);
@@ -256,13 +849,68 @@ impl AsMut<common::State> for State {
 
 impl Dispatch<WlRegistry, GlobalListContents> for State {
     fn event(
-        _state: &mut Self,
-        _proxy: &WlRegistry,
-        _event: <WlRegistry as wayland_client::Proxy>::Event,
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: <WlRegistry as wayland_client::Proxy>::Event,
         _data: &GlobalListContents,
+        conn: &wayland_client::Connection,
+        qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if state.offer_to_new_seats
+                && interface == WlSeat::interface().name
+                && version >= 2 =>
+            {
+                let seat: WlSeat = registry.bind(name, version.min(5), qhandle, ());
+                let data = SeatData {
+                    global_name: name,
+                    ..Default::default()
+                };
+                state.common.seats.insert(seat.clone(), data);
+
+                // Wait for the seat name before deciding whether to copy to the seat.
+                conn.display().sync(qhandle, seat);
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                let seat = state
+                    .common
+                    .seats
+                    .iter()
+                    .find(|(_, data)| data.global_name == name)
+                    .map(|(seat, _)| seat.clone());
+
+                let Some(seat) = seat else {
+                    return;
+                };
+                if let Some(mut data) = state.common.seats.remove(&seat) {
+                    if let Some(device) = &data.device {
+                        state.withdraw(device);
+                    }
+                    data.set_device(None);
+                }
+                release_seat(&seat);
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<WlCallback, WlSeat> for State {
+    fn event(
+        state: &mut Self,
+        _proxy: &WlCallback,
+        event: <WlCallback as wayland_client::Proxy>::Event,
+        seat: &WlSeat,
         _conn: &wayland_client::Connection,
-        _qhandle: &wayland_client::QueueHandle<Self>,
+        qhandle: &wayland_client::QueueHandle<Self>,
     ) {
+        if let wl_callback::Event::Done { .. } = event {
+            state.offer_to_new_seat(seat, qhandle);
+        }
     }
 }
 
@@ -272,7 +920,14 @@ impl_dispatch_device!(State, WlSeat, |state: &mut Self, event, seat| {
     match event {
         Event::DataOffer { id } => id.destroy(),
         Event::Finished => {
-            state.common.seats.get_mut(seat).unwrap().set_device(None);
+            // The seat may have been removed already.
+            let Some(data) = state.common.seats.get_mut(seat) else {
+                return;
+            };
+            if let Some(device) = data.device.take() {
+                state.withdraw(&device);
+                device.destroy();
+            }
         }
         Event::PrimarySelection { .. } => {
             state.got_primary_selection = true;
@@ -295,27 +950,52 @@ impl_dispatch_source!(State, |state: &mut Self,
                 return;
             }
 
+            let info = &state.sources[&source];
+
             // I'm not sure if it's the compositor's responsibility to check that the mime type is
             // valid. Let's check here just in case.
-            if !state.data_paths.contains_key(&mime_type) {
+            let data = match &info.restored {
+                Some(restored) => restored.get(&mime_type),
+                None => state.data.get(&mime_type),
+            };
+            let Some(data) = data else {
                 return;
-            }
+            };
 
-            let data_path = &state.data_paths[&mime_type];
+            let start = Instant::now();
 
-            let file = File::open(data_path).map_err(DataSourceError::FileOpen);
-            let result = file.and_then(|mut data_file| {
-                // Clear O_NONBLOCK, otherwise io::copy() will stop halfway.
-                fcntl_setfl(&fd, OFlags::empty())
-                    .map_err(io::Error::from)
-                    .map_err(DataSourceError::Copy)?;
+            // Clear O_NONBLOCK, otherwise io::copy() will stop halfway.
+            let result = fcntl_setfl(&fd, OFlags::empty())
+                .map_err(io::Error::from)
+                .map_err(DataSourceError::Copy)
+                .and_then(|()| data.write_to(&mime_type, &mut File::from(fd)));
 
-                let mut target_file = File::from(fd);
-                io::copy(&mut data_file, &mut target_file).map_err(DataSourceError::Copy)
-            });
+            // The previous clipboard contents are served on a best-effort basis, they don't count
+            // towards the copy.
+            if info.restored.is_some() {
+                if let Err(err) = result {
+                    trace!("Error serving the previous clipboard contents: {}", err);
+                }
+                return;
+            }
 
-            if let Err(err) = result {
-                state.error = Some(err);
+            let duration = start.elapsed();
+            let request = state.paste_request(&source, mime_type);
+            match result {
+                Ok(bytes) => state.emit(CopyEvent::Served {
+                    request,
+                    bytes,
+                    duration,
+                }),
+                Err(err) => {
+                    let error = Arc::new(err.duplicate());
+                    state.error = Some(err);
+                    state.emit(CopyEvent::Failed {
+                        request,
+                        error,
+                        duration,
+                    });
+                }
             }
 
             let done = if let ServeRequests::Only(left) = state.serve_requests {
@@ -331,7 +1011,20 @@ impl_dispatch_source!(State, |state: &mut Self,
                 source.destroy();
             }
         }
-        Event::Cancelled => source.destroy(),
+        Event::Cancelled => {
+            source.destroy();
+
+            let info = state.sources.get_mut(&source).unwrap();
+            info.cancelled = true;
+
+            if info.restored.is_none() {
+                let event = CopyEvent::Cancelled {
+                    seat: info.seat.clone(),
+                    clipboard: info.clipboard,
+                };
+                state.emit(event);
+            }
+        }
         _ => (),
     }
 });
@@ -401,6 +1094,104 @@ impl Options {
         self
     }
 
+    /// Sets the data-control protocol to use.
+    ///
+    /// See [`Protocol`].
+    #[inline]
+    pub fn protocol(&mut self, protocol: Protocol) -> &mut Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Sets the Wayland display to connect to.
+    ///
+    /// This is either a display name relative to `XDG_RUNTIME_DIR`, like `wayland-1`, or an
+    /// absolute path to the compositor socket. By default the compositor is found through the
+    /// `WAYLAND_SOCKET` and `WAYLAND_DISPLAY` environment variables.
+    ///
+    /// To use an existing connection, see [`Clipboard::from_connection()`].
+    #[inline]
+    pub fn display(&mut self, display: impl Into<OsString>) -> &mut Self {
+        self.display = Some(display.into());
+        self
+    }
+
+    /// Sets the storage for the copied data.
+    ///
+    /// By default the data is kept in memory if possible, falling back to temporary files
+    /// otherwise. Sources that are served as is, like data providers, are not affected.
+    #[inline]
+    pub fn storage(&mut self, storage: Storage) -> &mut Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Sets the time after which to stop serving copy requests.
+    ///
+    /// Once the time runs out, the data sources are destroyed and serving finishes, like when the
+    /// clipboard is taken over by some other application. It can be used when copying e.g.
+    /// sensitive data, like passwords. The time is counted from the moment the data is offered.
+    #[inline]
+    pub fn expire_after(&mut self, expire_after: Duration) -> &mut Self {
+        self.expire_after = Some(expire_after);
+        self
+    }
+
+    /// Sets the flag for clearing the clipboard upon expiry.
+    ///
+    /// If set, the clipboard is cleared when the copy expires, unless some other application took
+    /// it over in the meantime. Otherwise, what happens to the clipboard contents is up to the
+    /// compositor and any running clipboard managers.
+    #[inline]
+    pub fn clear_on_expiry(&mut self, clear_on_expiry: bool) -> &mut Self {
+        self.clear_on_expiry = clear_on_expiry;
+        self
+    }
+
+    /// Sets the flag for restoring the previous clipboard contents once done serving.
+    ///
+    /// If set, the current clipboard contents are read in all offered MIME types before copying.
+    /// Once the copy expires or the number of requests to serve runs out, the previous contents are
+    /// offered again, unless some other application took over the clipboard in the meantime. This
+    /// takes precedence over [`clear_on_expiry()`](Self::clear_on_expiry).
+    ///
+    /// The previous contents are served until the clipboard is taken over, so the serving
+    /// continues past the expiry or the last request.
+    #[inline]
+    pub fn restore_previous(&mut self, restore_previous: bool) -> &mut Self {
+        self.restore_previous = restore_previous;
+        self
+    }
+
+    /// Sets the time limit for reading the previous clipboard contents.
+    ///
+    /// Only used with [`restore_previous()`](Self::restore_previous). The limit covers reading
+    /// the contents in all MIME types from the client that copied them, which may never finish if
+    /// that client misbehaves. Copying fails with [`Error::Snapshot`] once it runs out.
+    #[inline]
+    pub fn snapshot_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.snapshot_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum size of the previous clipboard contents in bytes.
+    ///
+    /// Only used with [`restore_previous()`](Self::restore_previous). The limit applies to each
+    /// MIME type separately. Copying fails with [`Error::Snapshot`] if the contents exceed it.
+    #[inline]
+    pub fn snapshot_max_size(&mut self, max_size: u64) -> &mut Self {
+        self.snapshot_max_size = Some(max_size);
+        self
+    }
+
+    /// Clears the clipboard of the configured seats, using the configured protocol and display.
+    ///
+    /// The other options are not used. See `clear()`.
+    #[inline]
+    pub fn clear(self) -> Result<(), Error> {
+        clear_with_protocol(self.clipboard, self.seat, self.protocol, self.display)
+    }
+
     /// Invokes the copy operation. See `copy()`.
     ///
     /// # Examples
@@ -417,7 +1208,7 @@ impl Options {
     /// # }
     /// ```
     #[inline]
-    pub fn copy(self, source: Source, mime_type: MimeType) -> Result<(), Error> {
+    pub fn copy(self, source: Source, mime_type: MimeType) -> Result<CopyHandle, Error> {
         copy(self, source, mime_type)
     }
 
@@ -440,7 +1231,7 @@ impl Options {
     /// # }
     /// ```
     #[inline]
-    pub fn copy_multi(self, sources: Vec<MimeSource>) -> Result<(), Error> {
+    pub fn copy_multi(self, sources: Vec<MimeSource>) -> Result<CopyHandle, Error> {
         copy_multi(self, sources)
     }
 
@@ -509,45 +1300,238 @@ impl PreparedCopy {
     ///
     /// This function **blocks** until all requests are served or the clipboard is taken over by
     /// some other application.
-    pub fn serve(mut self) -> Result<(), Error> {
-        // Loop until we're done.
-        while !self.state.should_quit {
-            self.queue
-                .blocking_dispatch(&mut self.state)
-                .map_err(Error::WaylandCommunication)?;
-
-            // Check if all sources have been destroyed.
-            let all_destroyed = self.sources.iter().all(|x| !x.is_alive());
-            if all_destroyed {
-                self.state.should_quit = true;
-            }
-        }
+    pub fn serve(self) -> Result<(), Error> {
+        self.serve_internal(None)
+    }
 
-        // Clean up the temp file and directory.
-        //
-        // We want to try cleaning up all files and folders, so if any errors occur in process,
-        // collect them into a vector without interruption, and then return the first one.
-        let mut results = Vec::new();
-        let mut dropped = HashSet::new();
-        for data_path in self.state.data_paths.values_mut() {
-            // data_paths can contain duplicate items, we want to free each only once.
-            if dropped.contains(data_path) {
+    // Serves requests until done, or until the cancellation token is triggered.
+    fn serve_internal(mut self, cancel: Option<&CancelToken>) -> Result<(), Error> {
+        // Loop until we're done.
+        while !self.process()? {
+            // Prepare the read before waiting, so that the wait ends when some other event queue
+            // on the same connection reads our events.
+            let Some(guard) = self.queue.prepare_read() else {
                 continue;
             };
-            dropped.insert(data_path.clone());
 
-            match remove_file(&data_path).map_err(Error::TempFileRemove) {
-                Ok(()) => {
-                    data_path.pop();
-                    results.push(remove_dir(&data_path).map_err(Error::TempDirRemove));
+            // Some other event queue may have read our events right before the read was prepared.
+            if self.dispatch_pending()? > 0 {
+                continue;
+            }
+
+            let fd = guard.connection_fd();
+            let mut fds = vec![PollFd::new(&fd, PollFlags::IN)];
+            if let Some(cancel) = cancel {
+                fds.push(PollFd::new(&cancel.read, PollFlags::IN));
+            }
+
+            match poll(&mut fds, poll_timeout(self.expiry)) {
+                Ok(_) | Err(Errno::INTR) => (),
+                Err(err) => {
+                    return Err(Error::WaylandCommunication(
+                        WaylandError::Io(err.into()).into(),
+                    ))
                 }
-                result @ Err(_) => results.push(result),
             }
+
+            drop(fds);
+
+            if cancel.is_some_and(CancelToken::is_cancelled) {
+                break;
+            }
+
+            read_events(guard)?;
+        }
+
+        self.finish()
+    }
+
+    /// Sets the callback to invoke for every event that occurs while serving the copy.
+    ///
+    /// The callback is invoked from whichever function serves the requests, such as
+    /// [`serve()`](Self::serve) or [`poll()`](Self::poll), right after the event occurs.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate wl_clipboard_rs;
+    /// # use wl_clipboard_rs::copy::Error;
+    /// # fn foo() -> Result<(), Error> {
+    /// use wl_clipboard_rs::copy::{CopyEvent, MimeType, Options, Source};
+    ///
+    /// let mut opts = Options::new();
+    /// opts.foreground(true);
+    /// let mut prepared_copy = opts.prepare_copy(Source::Bytes([1, 2, 3][..].into()),
+    ///                                           MimeType::Autodetect)?;
+    /// prepared_copy.set_event_callback(|event| {
+    ///     if let CopyEvent::Served { request, .. } = event {
+    ///         println!("Pasted as {}", request.mime_type);
+    ///     }
+    /// });
+    /// prepared_copy.serve()?;
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_event_callback(&mut self, callback: impl FnMut(CopyEvent) + Send + 'static) {
+        self.state.event_callback = Some(Box::new(callback));
+    }
+
+    /// Serves the copy requests that are ready, without blocking.
+    ///
+    /// This is the building block for serving the copy from an external event loop. Register the
+    /// file descriptor of this `PreparedCopy` (it implements [`AsFd`]) for readability, and call
+    /// this function every time it becomes readable. It reads and dispatches the pending Wayland
+    /// events and flushes the outgoing requests.
+    ///
+    /// Returns `true` once serving is finished, i.e. all requests are served or the clipboard is
+    /// taken over by some other application. Call [`finish()`](Self::finish) at that point.
+    ///
+    /// It should also be called once before the first wait on the file descriptor, since it
+    /// flushes the requests that offer the data in the first place. If the copy has an expiry
+    /// time, it should be called at that time too, see [`expiry()`](Self::expiry).
+    pub fn poll(&mut self) -> Result<bool, Error> {
+        self.queue
+            .dispatch_pending(&mut self.state)
+            .map_err(Error::WaylandCommunication)?;
+
+        if let Some(guard) = self.queue.prepare_read() {
+            read_events(guard)?;
         }
 
-        // Return the error, if any.
-        let result: Result<(), _> = results.into_iter().collect();
-        result?;
+        self.process()
+    }
+
+    fn dispatch_pending(&mut self) -> Result<usize, Error> {
+        self.queue
+            .dispatch_pending(&mut self.state)
+            .map_err(Error::WaylandCommunication)
+    }
+
+    // Dispatches the events that were already read, and flushes the outgoing requests.
+    //
+    // Unlike poll(), this never reads from the connection. A read blocks while another event queue
+    // on the same connection is waiting for the events, which the serve loops have to avoid.
+    fn process(&mut self) -> Result<bool, Error> {
+        self.queue
+            .dispatch_pending(&mut self.state)
+            .map_err(Error::WaylandCommunication)?;
+        self.sources.append(&mut self.state.new_sources);
+
+        if !self.update_finished() && self.expiry.is_some_and(|expiry| Instant::now() >= expiry) {
+            self.expire();
+        }
+
+        // The last request was served.
+        if self.restore_previous
+            && self.state.should_quit
+            && self.state.error.is_none()
+            && self.state.serve_requests == ServeRequests::Only(0)
+        {
+            self.restore_previous();
+        }
+
+        // If the socket buffer is full, the rest is flushed on the next call.
+        match self.queue.flush() {
+            Err(WaylandError::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => (),
+            result => result.map_err(|err| Error::WaylandCommunication(err.into()))?,
+        }
+
+        Ok(self.update_finished())
+    }
+
+    /// Returns the time at which the copy expires, if any.
+    ///
+    /// See [`Options::expire_after()`].
+    pub fn expiry(&self) -> Option<Instant> {
+        self.expiry
+    }
+
+    /// Serves copy requests asynchronously.
+    ///
+    /// This is the async version of [`serve()`](Self::serve): the returned future completes once
+    /// all requests are served or the clipboard is taken over by some other application. It works
+    /// with any async runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate wl_clipboard_rs;
+    /// # use wl_clipboard_rs::copy::Error;
+    /// # async fn foo() -> Result<(), Error> {
+    /// use wl_clipboard_rs::copy::{MimeType, Options, Source};
+    ///
+    /// let mut opts = Options::new();
+    /// opts.foreground(true);
+    /// let prepared_copy = opts.prepare_copy(Source::Bytes([1, 2, 3][..].into()),
+    ///                                       MimeType::Autodetect)?;
+    /// prepared_copy.serve_async().await?;
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn serve_async(mut self) -> Result<(), Error> {
+        // Register a duplicate of the connection fd, so that polling doesn't need to borrow self.
+        let fd = self
+            .queue
+            .as_fd()
+            .try_clone_to_owned()
+            .and_then(async_io::Async::new)
+            .map_err(Error::AsyncRegistration)?;
+
+        while !self.process()? {
+            // See serve_internal() for why the read is prepared before waiting.
+            let Some(guard) = self.queue.prepare_read() else {
+                continue;
+            };
+
+            if self.dispatch_pending()? > 0 {
+                continue;
+            }
+
+            let readable = fd.readable();
+            let result = match self.expiry {
+                Some(expiry) => {
+                    let expired = async {
+                        async_io::Timer::at(expiry).await;
+                        Ok(())
+                    };
+                    futures_lite::future::or(readable, expired).await
+                }
+                None => readable.await,
+            };
+            result.map_err(|err| Error::WaylandCommunication(WaylandError::Io(err).into()))?;
+
+            read_events(guard)?;
+        }
+
+        self.finish()
+    }
+
+    /// Stops serving copy requests and cleans up.
+    ///
+    /// The data sources that are still alive are destroyed, withdrawing the offer from the
+    /// clipboard, and the temporary files are removed. Returns an error if one occurred while
+    /// serving a paste request.
+    ///
+    /// There's no need to call this function after [`serve()`](Self::serve), which does it
+    /// automatically.
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.sources.append(&mut self.state.new_sources);
+        for source in &self.sources {
+            if source.is_alive() {
+                source.destroy();
+            }
+        }
+        let _ = self.queue.flush();
+
+        // Clean up the temp files, including the ones with the previous clipboard contents.
+        let previous = self.state.sources.values().flat_map(|info| {
+            let previous = info.previous.iter().chain(&info.restored);
+            previous.flat_map(HashMap::values)
+        });
+        remove_temp_files(self.state.data.values().chain(previous))?;
 
         // Check if an error occurred during data transfer.
         if let Some(err) = self.state.error.take() {
@@ -556,38 +1540,344 @@ impl PreparedCopy {
 
         Ok(())
     }
+
+    // Stops serving because the expiry time has passed.
+    fn expire(&mut self) {
+        self.state.emit(CopyEvent::Expired);
+
+        if self.restore_previous {
+            self.restore_previous();
+            return;
+        }
+
+        for source in &self.sources {
+            // Dead sources have been cancelled, meaning that we no longer own the selection.
+            if !source.is_alive() {
+                continue;
+            }
+
+            if self.clear_on_expiry {
+                let info = &self.state.sources[source];
+                if info.clipboard == ClipboardType::Primary {
+                    info.device.set_primary_selection(None);
+                } else {
+                    info.device.set_selection(None);
+                }
+            }
+
+            source.destroy();
+        }
+
+        self.state.should_quit = true;
+    }
+
+    // Replaces the copied data with the previous clipboard contents.
+    fn restore_previous(&mut self) {
+        self.restore_previous = false;
+        self.expiry = None;
+        self.state.should_quit = false;
+        // The seats that appear from now on don't get the copied data.
+        self.state.offer_to_new_seats = false;
+        self.sources.append(&mut self.state.new_sources);
+
+        let qh = self.queue.handle();
+        let mut restored = Vec::new();
+        for source in mem::take(&mut self.sources) {
+            if source.is_alive() {
+                source.destroy();
+            }
+
+            let info = self.state.sources.get_mut(&source).unwrap();
+
+            // Some other client owns the selection now, leave it be.
+            if info.cancelled {
+                continue;
+            }
+
+            let primary = info.clipboard == ClipboardType::Primary;
+
+            // If the clipboard was empty, make it empty again.
+            let Some(previous) = info.previous.take() else {
+                if primary {
+                    info.device.set_primary_selection(None);
+                } else {
+                    info.device.set_selection(None);
+                }
+                continue;
+            };
+
+            let data_source = self.state.common.clipboard_manager.create_data_source(&qh);
+            for mime_type in previous.keys() {
+                data_source.offer(mime_type.clone());
+            }
+
+            if primary {
+                info.device.set_primary_selection(Some(&data_source));
+            } else {
+                info.device.set_selection(Some(&data_source));
+            }
+
+            let info = SourceInfo {
+                seat: info.seat.clone(),
+                device: info.device.clone(),
+                clipboard: info.clipboard,
+                cancelled: false,
+                previous: None,
+                restored: Some(previous),
+            };
+            self.state.sources.insert(data_source.clone(), info);
+            restored.push(data_source);
+        }
+
+        self.sources = restored;
+    }
+
+    // Checks whether serving is finished.
+    fn update_finished(&mut self) -> bool {
+        // Check if all sources have been destroyed.
+        let all_destroyed = self.sources.iter().all(|x| !x.is_alive());
+        if all_destroyed {
+            self.state.should_quit = true;
+        }
+
+        self.state.should_quit
+    }
 }
 
-fn make_source(
-    source: Source,
-    mime_type: MimeType,
-    trim_newline: bool,
-) -> Result<(String, PathBuf), SourceCreationError> {
+impl AsFd for PreparedCopy {
+    /// Returns the file descriptor of the Wayland connection, for polling.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.queue.as_fd()
+    }
+}
+
+fn remove_temp_file(path: &Path) -> Result<(), Error> {
+    remove_file(path).map_err(Error::TempFileRemove)?;
+    remove_dir(path.parent().unwrap()).map_err(Error::TempDirRemove)
+}
+
+fn autodetect_mime_type(file: &File, hint: Option<&Path>) -> Result<String, SourceCreationError> {
+    // Prefer the MIME type suggested by the file extension, if there is one.
+    if let Some(mime) = hint.and_then(|hint| mime_guess::from_path(hint).first()) {
+        return Ok(mime.to_string());
+    }
+
+    // Make sure the data is sniffed from the start.
+    let mut file = file;
+    file.seek(SeekFrom::Start(0))
+        .map_err(SourceCreationError::TempFileSeek)?;
+
+    match tree_magic_mini::from_file(file) {
+        Some(magic) => Ok(magic.to_string()),
+        None => Err(SourceCreationError::TempFileOpen(std::io::Error::other(
+            "problem with temp file",
+        ))),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn create_memfd() -> io::Result<File> {
+    let fd = memfd_create(
+        "wl-clipboard-rs",
+        MemfdFlags::CLOEXEC | MemfdFlags::ALLOW_SEALING,
+    )?;
+    Ok(File::from(fd))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn create_memfd() -> io::Result<File> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+fn seal_memfd(file: &File) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    fcntl_add_seals(
+        file,
+        SealFlags::SHRINK | SealFlags::GROW | SealFlags::WRITE | SealFlags::SEAL,
+    )?;
+
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+    let _ = file;
+
+    Ok(())
+}
+
+// Reads the events from the connection, if there are any.
+fn read_events(guard: ReadEventsGuard) -> Result<(), Error> {
+    common::read_events(guard).map_err(|err| Error::WaylandCommunication(err.into()))
+}
+
+// Removes the temp files among the data.
+fn remove_temp_files<'a>(data: impl Iterator<Item = &'a Data>) -> Result<(), Error> {
+    // We want to try cleaning up all files and folders, so if any errors occur in process,
+    // collect them into a vector without interruption, and then return the first one.
+    let mut results = Vec::new();
+    let mut dropped = HashSet::new();
+    for data in data {
+        let Data::TempFile(data_path) = data else {
+            continue;
+        };
+
+        // data can contain duplicate items, we want to free each only once.
+        if !dropped.insert(data_path) {
+            continue;
+        }
+
+        results.push(remove_temp_file(data_path));
+    }
+
+    // Return the error, if any.
+    results.into_iter().collect()
+}
+
+// Reads the current contents of a clipboard in all offered MIME types, giving up at the deadline or
+// once the contents in some MIME type exceed the maximum size.
+//
+// Returns `None` if the clipboard is empty.
+fn snapshot(
+    clipboard: &mut Clipboard,
+    seat: Option<&str>,
+    primary: bool,
+    storage: Storage,
+    max_size: Option<u64>,
+    deadline: Option<Instant>,
+) -> Result<Option<HashMap<String, Data>>, Error> {
+    let clipboard_type = if primary {
+        paste::ClipboardType::Primary
+    } else {
+        paste::ClipboardType::Regular
+    };
+    let seat = seat.map_or(paste::Seat::Unspecified, paste::Seat::Specific);
+
+    let mime_types = match clipboard.mime_types(clipboard_type, seat) {
+        Ok(mime_types) => mime_types,
+        Err(paste::Error::ClipboardEmpty | paste::Error::NoMimeType) => return Ok(None),
+        Err(err) => return Err(Error::Snapshot(err)),
+    };
+
+    let mut snapshot = HashMap::new();
+    for mime_type in mime_types {
+        let result = clipboard
+            .contents(clipboard_type, seat, paste::MimeType::Specific(&mime_type))
+            .and_then(|(read, _)| paste::read_pipe(read, max_size, deadline))
+            .map_err(Error::Snapshot)
+            .and_then(|contents| {
+                let source = Source::Bytes(contents.into());
+                make_source(source, MimeType::Specific(mime_type), false, storage)
+                    .map_err(Error::TempCopy)
+            });
+
+        match result {
+            Ok((mime_type, data)) => {
+                snapshot.insert(mime_type, data);
+            }
+            Err(err) => {
+                let _ = remove_temp_files(snapshot.values());
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(Some(snapshot))
+}
+
+/// Creates the storage for the source data.
+///
+/// Returns the storage file, opened for reading and writing, and its path if it's a temporary
+/// file.
+fn create_storage(storage: Storage) -> Result<(File, Option<PathBuf>), SourceCreationError> {
+    if storage != Storage::TempFile {
+        match create_memfd() {
+            Ok(file) => return Ok((file, None)),
+            Err(err) if storage == Storage::Memory => {
+                return Err(SourceCreationError::MemfdCreate(err))
+            }
+            Err(err) => trace!(
+                "Couldn't create a memfd, falling back to a temp file: {}",
+                err
+            ),
+        }
+    }
+
     let temp_dir = tempfile::tempdir().map_err(SourceCreationError::TempDirCreate)?;
     let mut temp_filename = temp_dir.into_path();
     temp_filename.push("stdin");
     trace!("Temp filename: {}", temp_filename.to_string_lossy());
-    let mut temp_file =
-        File::create(&temp_filename).map_err(SourceCreationError::TempFileCreate)?;
+    let temp_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&temp_filename)
+        .map_err(SourceCreationError::TempFileCreate)?;
+
+    Ok((temp_file, Some(temp_filename)))
+}
 
-    if let Source::Bytes(data) = source {
-        temp_file
-            .write_all(&data)
-            .map_err(SourceCreationError::TempFileWrite)?;
-    } else {
-        // Copy the standard input into the target file.
-        io::copy(&mut io::stdin(), &mut temp_file).map_err(SourceCreationError::DataCopy)?;
+pub(crate) fn make_source(
+    source: Source,
+    mime_type: MimeType,
+    trim_newline: bool,
+    storage: Storage,
+) -> Result<(String, Data), SourceCreationError> {
+    // Providers generate the data on demand, so there's nothing to store.
+    if let Source::Provider(provider) = source {
+        let mime_type = match mime_type {
+            MimeType::Autodetect => return Err(SourceCreationError::ProviderAutodetect),
+            MimeType::Text => "text/plain".to_string(),
+            MimeType::Specific(mime_type) => mime_type,
+        };
+
+        trace!("Base MIME type: {}", mime_type);
+
+        return Ok((mime_type, Data::Provider(provider)));
+    }
+
+    // Files that don't need a snapshot are served as is.
+    if let Source::File {
+        path,
+        snapshot: false,
+    } = source
+    {
+        let file = File::open(&path).map_err(SourceCreationError::FileOpen)?;
+
+        let mime_type = match mime_type {
+            MimeType::Autodetect => autodetect_mime_type(&file, Some(&path))?,
+            MimeType::Text => "text/plain".to_string(),
+            MimeType::Specific(mime_type) => mime_type,
+        };
+
+        trace!("Base MIME type: {}", mime_type);
+
+        return Ok((mime_type, Data::File(path)));
+    }
+
+    let (mut temp_file, temp_filename) = create_storage(storage)?;
+
+    let mut hint = None;
+    match source {
+        Source::Bytes(data) => {
+            temp_file
+                .write_all(&data)
+                .map_err(SourceCreationError::TempFileWrite)?;
+        }
+        Source::StdIn => {
+            // Copy the standard input into the target file.
+            io::copy(&mut io::stdin(), &mut temp_file).map_err(SourceCreationError::DataCopy)?;
+        }
+        Source::Reader(reader) => {
+            let mut reader = reader.lock().unwrap_or_else(PoisonError::into_inner);
+            io::copy(&mut *reader, &mut temp_file).map_err(SourceCreationError::DataCopy)?;
+        }
+        Source::File { path, .. } => {
+            let mut file = File::open(&path).map_err(SourceCreationError::FileOpen)?;
+            io::copy(&mut file, &mut temp_file).map_err(SourceCreationError::DataCopy)?;
+            hint = Some(path);
+        }
+        Source::Provider(_) => unreachable!(),
     }
 
     let mime_type = match mime_type {
-        MimeType::Autodetect => match tree_magic_mini::from_filepath(&temp_filename) {
-            Some(magic) => Ok(magic),
-            None => Err(SourceCreationError::TempFileOpen(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "problem with temp file",
-            ))),
-        }?
-        .to_string(),
+        MimeType::Autodetect => autodetect_mime_type(&temp_file, hint.as_deref())?,
         MimeType::Text => "text/plain".to_string(),
         MimeType::Specific(mime_type) => mime_type,
     };
@@ -596,11 +1886,6 @@ fn make_source(
 
     // Trim the trailing newline if needed.
     if trim_newline && is_text(&mime_type) {
-        let mut temp_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(&temp_filename)
-            .map_err(SourceCreationError::TempFileOpen)?;
         let metadata = temp_file
             .metadata()
             .map_err(SourceCreationError::TempFileMetadata)?;
@@ -622,15 +1907,26 @@ fn make_source(
         }
     }
 
-    Ok((mime_type, temp_filename))
+    match temp_filename {
+        Some(temp_filename) => Ok((mime_type, Data::TempFile(temp_filename))),
+        None => {
+            // Nothing is going to write to the memfd anymore.
+            seal_memfd(&temp_file).map_err(SourceCreationError::MemfdSeal)?;
+            Ok((mime_type, Data::Memory(Arc::new(temp_file))))
+        }
+    }
 }
 
+// Data device along with the name of its seat.
+type SeatDevice = (Option<String>, data_control::Device);
+
 fn get_devices(
     primary: bool,
     seat: Seat,
-    socket_name: Option<OsString>,
-) -> Result<(EventQueue<State>, State, Vec<data_control::Device>), Error> {
-    let (mut queue, mut common) = initialize(primary, socket_name)?;
+    protocol: Protocol,
+    conn: &Connection,
+) -> Result<(EventQueue<State>, State, Vec<SeatDevice>), Error> {
+    let (mut queue, mut common) = initialize_with(conn, protocol, wlr_versions(primary))?;
 
     // Check if there are no seats.
     if common.seats.is_empty() {
@@ -645,14 +1941,7 @@ fn get_devices(
         data.set_device(Some(device));
     }
 
-    let mut state = State {
-        common,
-        got_primary_selection: false,
-        should_quit: false,
-        data_paths: HashMap::new(),
-        serve_requests: ServeRequests::default(),
-        error: None,
-    };
+    let mut state = State::new(common);
 
     // Retrieve all seat names.
     queue
@@ -664,39 +1953,7 @@ fn get_devices(
         return Err(Error::PrimarySelectionUnsupported);
     }
 
-    // Figure out which devices we're interested in.
-    let devices = state
-        .common
-        .seats
-        .values()
-        .filter_map(|data| {
-            let SeatData { name, device, .. } = data;
-
-            let device = device.clone();
-
-            match seat {
-                Seat::All => {
-                    // If no seat was specified, handle all of them.
-                    return device;
-                }
-                Seat::Specific(ref desired_name) => {
-                    if name.as_deref() == Some(desired_name) {
-                        return device;
-                    }
-                }
-            }
-
-            None
-        })
-        .collect::<Vec<_>>();
-
-    // If we didn't find the seat, print an error message and exit.
-    //
-    // This also triggers when we found the seat but it had no data device; is this what we want?
-    if devices.is_empty() {
-        return Err(Error::SeatNotFound);
-    }
-
+    let devices = state.devices(&seat)?;
     Ok((queue, state, devices))
 }
 
@@ -725,11 +1982,21 @@ pub(crate) fn clear_internal(
     clipboard: ClipboardType,
     seat: Seat,
     socket_name: Option<OsString>,
+) -> Result<(), Error> {
+    clear_with_protocol(clipboard, seat, Protocol::Auto, socket_name)
+}
+
+fn clear_with_protocol(
+    clipboard: ClipboardType,
+    seat: Seat,
+    protocol: Protocol,
+    socket_name: Option<OsString>,
 ) -> Result<(), Error> {
     let primary = clipboard != ClipboardType::Regular;
-    let (mut queue, mut state, devices) = get_devices(primary, seat, socket_name)?;
+    let conn = connect(socket_name)?;
+    let (mut queue, mut state, devices) = get_devices(primary, seat, protocol, &conn)?;
 
-    for device in devices {
+    for (_, device) in devices {
         if clipboard == ClipboardType::Primary || clipboard == ClipboardType::Both {
             device.set_primary_selection(None);
         }
@@ -838,40 +2105,100 @@ pub fn prepare_copy_multi(
     prepare_copy_internal(options, sources, None)
 }
 
-fn prepare_copy_internal(
+pub(crate) fn prepare_copy_internal(
     options: Options,
     sources: Vec<MimeSource>,
     socket_name: Option<OsString>,
+) -> Result<PreparedCopy, Error> {
+    let conn = connect(socket_name.or_else(|| options.display.clone()))?;
+    prepare_copy_with(&conn, options, sources)
+}
+
+pub(crate) fn prepare_copy_with(
+    conn: &Connection,
+    options: Options,
+    sources: Vec<MimeSource>,
+) -> Result<PreparedCopy, Error> {
+    let primary = options.clipboard != ClipboardType::Regular;
+    let (queue, mut state, devices) =
+        get_devices(primary, options.seat.clone(), options.protocol, conn)?;
+    state.offer_to_new_seats = true;
+
+    // The previous clipboard contents are read through a clipboard on the same connection.
+    let mut clipboard = None;
+    if options.restore_previous {
+        let snapshot_clipboard = Clipboard::from_connection(conn.clone(), options.protocol)
+            .map_err(|err| Error::Snapshot(err.into()))?;
+        clipboard = Some(snapshot_clipboard);
+    }
+
+    prepare_copy_on(queue, state, devices, clipboard.as_mut(), options, sources)
+}
+
+// Prepares a copy reusing the clipboard manager, the seats and the data devices of `clipboard`.
+//
+// The data devices deliver their events to the clipboard's event queue, so the seats that appear
+// while serving are not copied to.
+pub(crate) fn prepare_copy_shared(
+    clipboard: &mut Clipboard,
+    options: Options,
+    sources: Vec<MimeSource>,
+) -> Result<PreparedCopy, Error> {
+    let primary = options.clipboard != ClipboardType::Regular;
+    let (conn, common) = clipboard.shared_state(primary)?;
+
+    let queue = conn.new_event_queue();
+    let state = State::new(common);
+    let devices = state.devices(&options.seat)?;
+
+    prepare_copy_on(queue, state, devices, Some(clipboard), options, sources)
+}
+
+// Reads the sources and offers them on the given devices.
+//
+// `clipboard` is used for reading the previous clipboard contents if they are to be restored.
+fn prepare_copy_on(
+    queue: EventQueue<State>,
+    mut state: State,
+    devices: Vec<SeatDevice>,
+    clipboard: Option<&mut Clipboard>,
+    options: Options,
+    sources: Vec<MimeSource>,
 ) -> Result<PreparedCopy, Error> {
     let Options {
-        clipboard,
+        clipboard: clipboard_type,
         seat,
         trim_newline,
         serve_requests,
+        storage,
+        expire_after,
+        clear_on_expiry,
+        restore_previous,
+        snapshot_timeout,
+        snapshot_max_size,
         ..
     } = options;
 
-    let primary = clipboard != ClipboardType::Regular;
-    let (queue, mut state, devices) = get_devices(primary, seat, socket_name)?;
-
     state.serve_requests = serve_requests;
+    state.clipboard = clipboard_type;
+    state.seat = seat;
 
     // Collect the source data to copy.
-    state.data_paths = {
+    state.data = {
         let mut data_paths = HashMap::new();
         let mut text_data_path = None;
         for MimeSource { source, mime_type } in sources.into_iter() {
-            let (mime_type, mut data_path) =
-                make_source(source, mime_type, trim_newline).map_err(Error::TempCopy)?;
+            let (mime_type, data_path) =
+                make_source(source, mime_type, trim_newline, storage).map_err(Error::TempCopy)?;
 
             let mime_type_is_text = is_text(&mime_type);
 
             match data_paths.entry(mime_type) {
                 Entry::Occupied(_) => {
                     // This MIME type has already been specified, so ignore it.
-                    remove_file(&*data_path).map_err(Error::TempFileRemove)?;
-                    data_path.pop();
-                    remove_dir(&*data_path).map_err(Error::TempDirRemove)?;
+                    if let Data::TempFile(data_path) = data_path {
+                        remove_temp_file(&data_path)?;
+                    }
                 }
                 Entry::Vacant(entry) => {
                     if !options.omit_additional_text_mime_types
@@ -911,15 +2238,15 @@ fn prepare_copy_internal(
     // This is needed because for ClipboardType::Both each device needs to appear twice because
     // separate data sources need to be made for the regular and the primary clipboards (data
     // sources cannot be reused).
-    let devices_iter = devices.iter().flat_map(|device| {
-        let first = match clipboard {
-            ClipboardType::Regular => iter::once((device, false)),
-            ClipboardType::Primary => iter::once((device, true)),
-            ClipboardType::Both => iter::once((device, false)),
+    let devices_iter = devices.iter().flat_map(|(name, device)| {
+        let first = match clipboard_type {
+            ClipboardType::Regular => iter::once((name, device, false)),
+            ClipboardType::Primary => iter::once((name, device, true)),
+            ClipboardType::Both => iter::once((name, device, false)),
         };
 
-        let second = if clipboard == ClipboardType::Both {
-            iter::once(Some((device, true)))
+        let second = if clipboard_type == ClipboardType::Both {
+            iter::once(Some((name, device, true)))
         } else {
             iter::once(None)
         };
@@ -927,29 +2254,37 @@ fn prepare_copy_internal(
         first.chain(second.flatten())
     });
 
-    // Create the data sources and set them as selections.
-    let sources = devices_iter
-        .map(|(device, primary)| {
-            let data_source = state
-                .common
-                .clipboard_manager
-                .create_data_source(&queue.handle());
-
-            for mime_type in state.data_paths.keys() {
-                data_source.offer(mime_type.clone());
-            }
-
-            if primary {
-                device.set_primary_selection(Some(&data_source));
-            } else {
-                device.set_selection(Some(&data_source));
+    // Read the current clipboard contents before replacing them.
+    let mut previous = Vec::new();
+    if let Some(clipboard) = clipboard.filter(|_| restore_previous) {
+        let deadline = snapshot_timeout.map(|timeout| Instant::now() + timeout);
+
+        for (name, _, primary) in devices_iter.clone() {
+            match snapshot(
+                clipboard,
+                name.as_deref(),
+                primary,
+                storage,
+                snapshot_max_size,
+                deadline,
+            ) {
+                Ok(data) => previous.push(data),
+                Err(err) => {
+                    let data = previous.iter().flatten().flat_map(HashMap::values);
+                    let _ = remove_temp_files(data.chain(state.data.values()));
+                    return Err(err);
+                }
             }
+        }
+    }
+    let mut previous = previous.into_iter();
 
-            // If we need to serve 0 requests, kill the data source right away.
-            if let ServeRequests::Only(0) = state.serve_requests {
-                data_source.destroy();
-            }
-            data_source
+    // Create the data sources and set them as selections.
+    let qh = queue.handle();
+    let sources = devices_iter
+        .map(|(name, device, primary)| {
+            let previous = previous.next().flatten();
+            state.offer(&qh, name.clone(), device.clone(), primary, previous)
         })
         .collect::<Vec<_>>();
 
@@ -957,6 +2292,9 @@ fn prepare_copy_internal(
         queue,
         state,
         sources,
+        expiry: expire_after.map(|duration| Instant::now() + duration),
+        clear_on_expiry,
+        restore_previous,
     })
 }
 
@@ -965,6 +2303,9 @@ fn prepare_copy_internal(
 /// The data is copied from `source` and offered in the `mime_type` MIME type. See `Options` for
 /// customizing the behavior of this operation.
 ///
+/// Unless the foreground mode is set, the requests are served on a separate thread, which can be
+/// controlled with the returned [`CopyHandle`].
+///
 /// # Examples
 ///
 /// ```no_run
@@ -979,7 +2320,7 @@ fn prepare_copy_internal(
 /// # }
 /// ```
 #[inline]
-pub fn copy(options: Options, source: Source, mime_type: MimeType) -> Result<(), Error> {
+pub fn copy(options: Options, source: Source, mime_type: MimeType) -> Result<CopyHandle, Error> {
     let sources = vec![MimeSource { source, mime_type }];
     copy_internal(options, sources, None)
 }
@@ -993,6 +2334,9 @@ pub fn copy(options: Options, source: Source, mime_type: MimeType) -> Result<(),
 /// types is text, all automatically added plain text offers will fall back to the first source
 /// with a text MIME type.
 ///
+/// Unless the foreground mode is set, the requests are served on a separate thread, which can be
+/// controlled with the returned [`CopyHandle`].
+///
 /// # Examples
 ///
 /// ```no_run
@@ -1010,7 +2354,7 @@ pub fn copy(options: Options, source: Source, mime_type: MimeType) -> Result<(),
 /// # }
 /// ```
 #[inline]
-pub fn copy_multi(options: Options, sources: Vec<MimeSource>) -> Result<(), Error> {
+pub fn copy_multi(options: Options, sources: Vec<MimeSource>) -> Result<CopyHandle, Error> {
     copy_internal(options, sources, None)
 }
 
@@ -1018,31 +2362,68 @@ pub(crate) fn copy_internal(
     options: Options,
     sources: Vec<MimeSource>,
     socket_name: Option<OsString>,
-) -> Result<(), Error> {
-    if options.foreground {
-        prepare_copy_internal(options, sources, socket_name)?.serve()
+) -> Result<CopyHandle, Error> {
+    let conn = connect(socket_name.or_else(|| options.display.clone()))?;
+    copy_with(conn, options, sources)
+}
+
+pub(crate) fn copy_with(
+    conn: Connection,
+    options: Options,
+    sources: Vec<MimeSource>,
+) -> Result<CopyHandle, Error> {
+    let foreground = options.foreground;
+    serve_copy(foreground, move || {
+        prepare_copy_with(&conn, options, sources)
+    })
+}
+
+// Serves the copy made by `prepare`, on a separate thread unless `foreground` is set.
+pub(crate) fn serve_copy(
+    foreground: bool,
+    prepare: impl FnOnce() -> Result<PreparedCopy, Error> + Send + 'static,
+) -> Result<CopyHandle, Error> {
+    if foreground {
+        prepare()?.serve()?;
+
+        Ok(CopyHandle {
+            thread: None,
+            cancel: None,
+            events: None,
+        })
     } else {
-        // The copy must be prepared on the thread because PreparedCopy isn't Send.
-        // To receive errors from prepare_copy, use a channel.
+        let cancel = Arc::new(CancelToken::new().map_err(Error::PipeCreation)?);
+
+        // The copy is prepared on the thread. To receive errors from prepare(), use a channel.
         let (tx, rx) = sync_channel(1);
+        let (events_tx, events_rx) = channel();
+
+        let thread = thread::spawn({
+            let cancel = cancel.clone();
+            move || match prepare() {
+                Ok(mut prepared_copy) => {
+                    prepared_copy.set_event_callback(move |event| drop(events_tx.send(event)));
 
-        thread::spawn(
-            move || match prepare_copy_internal(options, sources, socket_name) {
-                Ok(prepared_copy) => {
                     // prepare_copy completed successfully, report that.
                     drop(tx.send(None));
 
-                    // There's nobody listening for errors at this point, just drop it.
-                    drop(prepared_copy.serve());
+                    prepared_copy.serve_internal(Some(&cancel))
                 }
-                Err(err) => drop(tx.send(Some(err))),
-            },
-        );
+                Err(err) => {
+                    drop(tx.send(Some(err)));
+                    Ok(())
+                }
+            }
+        });
 
         if let Some(err) = rx.recv().unwrap() {
             return Err(err);
         }
 
-        Ok(())
+        Ok(CopyHandle {
+            thread: Some(thread),
+            cancel: Some(cancel),
+            events: Some(events_rx),
+        })
     }
 }
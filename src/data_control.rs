@@ -21,13 +21,13 @@ pub enum Manager {
     Ext(ExtDataControlManagerV1),
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub enum Device {
     Zwlr(ZwlrDataControlDeviceV1),
     Ext(ExtDataControlDeviceV1),
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub enum Source {
     Zwlr(ZwlrDataControlSourceV1),
     Ext(ExtDataControlSourceV1),
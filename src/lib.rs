@@ -21,6 +21,9 @@
 //! to link to `libwayland-client.so` for communication instead. A `dlopen` feature is also
 //! available for loading `libwayland-client.so` dynamically at runtime rather than linking to it.
 //!
+//! The `async` feature enables an async API for copying and pasting, which works with any async
+//! runtime: `copy::PreparedCopy::serve_async` and `paste::get_contents_async`.
+//!
 //! The code of the crate itself (and the code of the example utilities) is 100% safe Rust. This
 //! doesn't include the dependencies.
 //!
@@ -104,6 +107,10 @@
 //!   [wl-clipboard](https://github.com/bugaevc/wl-clipboard).
 //! - `wl-copy`: implements `wl-copy` from [wl-clipboard](https://github.com/bugaevc/wl-clipboard).
 //! - `wl-clip`: a Wayland version of `xclip`.
+//! - `wl-clip-persist`: keeps the clipboard contents after the copying application exits.
+//! - `wl-clip-historyd` and `wl-clip-history`: record the clipboard history and bring back
+//!   earlier entries.
+//! - `wl-clip-probe`: prints the clipboard capabilities of the compositor, for bug reports.
 
 #![doc(html_root_url = "https://docs.rs/wl-clipboard-rs/0.9.1")]
 #![deny(unsafe_code)]
@@ -116,6 +123,8 @@ mod seat_data;
 #[allow(unsafe_code)] // It's more convenient for testing some stuff.
 mod tests;
 
+pub mod clipboard;
 pub mod copy;
 pub mod paste;
+pub mod snapshot;
 pub mod utils;
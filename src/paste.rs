@@ -2,20 +2,37 @@
 
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
-use std::io;
+#[cfg(feature = "async")]
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::ControlFlow;
 use std::os::fd::AsFd;
+#[cfg(feature = "async")]
+use std::os::fd::OwnedFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use os_pipe::{pipe, PipeReader};
+use rustix::event::{poll, PollFd, PollFlags};
+use rustix::io::Errno;
+use wayland_backend::client::WaylandError;
+#[cfg(feature = "async")]
+use wayland_client::globals::Global;
 use wayland_client::globals::GlobalListContents;
+use wayland_client::protocol::wl_callback::{self, WlCallback};
+#[cfg(feature = "async")]
+use wayland_client::protocol::wl_registry;
 use wayland_client::protocol::wl_registry::WlRegistry;
 use wayland_client::protocol::wl_seat::WlSeat;
 use wayland_client::{
-    delegate_dispatch, event_created_child, ConnectError, Dispatch, DispatchError, EventQueue,
+    delegate_dispatch, event_created_child, ConnectError, Connection, Dispatch, DispatchError,
+    EventQueue, QueueHandle,
 };
 
-use crate::common::{self, initialize};
+use crate::common::{self, connect, initialize_with, poll_timeout, read_events, wlr_versions};
 use crate::data_control::{self, impl_dispatch_device, impl_dispatch_manager, impl_dispatch_offer};
-use crate::utils::is_text;
+use crate::utils::{describe_protocol, is_text, mime_type_matches, Protocol};
 
 /// The clipboard to operate on.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord, Default)]
@@ -36,9 +53,9 @@ pub enum ClipboardType {
 pub enum MimeType<'a> {
     /// Request any available MIME type.
     ///
-    /// If multiple MIME types are offered, the requested MIME type is unspecified and depends on
-    /// the order they are received from the Wayland compositor. However, plain text formats are
-    /// prioritized, so if a plain text format is available among others then it will be requested.
+    /// Plain text formats are prioritized, so if a plain text format is available among others
+    /// then it will be requested. Otherwise, the MIME type offered first is requested, in the
+    /// order returned by [`get_mime_types_ordered()`].
     Any,
     /// Request a plain text MIME type.
     ///
@@ -52,6 +69,16 @@ pub enum MimeType<'a> {
     TextWithPriority(&'a str),
     /// Request a specific MIME type.
     Specific(&'a str),
+    /// Request the first available MIME type from a list of preferences.
+    ///
+    /// The preferences are tried in order. Each of them is a pattern that can contain wildcards,
+    /// like `image/*` or `text/*;charset=utf-8`, and is matched with
+    /// [`mime_type_matches()`](crate::utils::mime_type_matches). If a pattern matches several
+    /// offered MIME types, the one offered first is requested.
+    ///
+    /// Example use-case: an image viewer can request `["image/png", "image/*"]` to get PNG if
+    /// possible, and any other image format otherwise.
+    Preferences(&'a [&'a str]),
 }
 
 /// Seat to operate on.
@@ -66,12 +93,137 @@ pub enum Seat<'a> {
     Specific(&'a str),
 }
 
-struct State {
-    common: common::State,
-    // The value is the set of MIME types in the offer.
-    // TODO: We never remove offers from here, even if we don't use them or after destroying them.
-    offers: HashMap<data_control::Offer, HashSet<String>>,
-    got_primary_selection: bool,
+/// Options and limits that are used to customize the pasting.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Options {
+    /// Time limit for pasting.
+    timeout: Option<Duration>,
+
+    /// Maximum size of the contents in bytes.
+    ///
+    /// When pasting all MIME types, the limit applies to each of them separately.
+    max_size: Option<u64>,
+
+    /// MIME types to paste when pasting all MIME types, `None` means all of them.
+    allowed_mime_types: Option<HashSet<String>>,
+
+    /// MIME types to skip when pasting all MIME types.
+    denied_mime_types: HashSet<String>,
+
+    /// Data-control protocol to use.
+    pub(crate) protocol: Protocol,
+
+    /// Wayland display to connect to, `None` means the one from the environment.
+    pub(crate) display: Option<OsString>,
+}
+
+/// Offer of the clipboard contents.
+///
+/// Yielded by [`Watcher`]. The offer becomes invalid once the clipboard contents change again,
+/// receiving from an invalid offer results in empty contents.
+pub struct Offer {
+    offer: data_control::Offer,
+    mime_types: Vec<String>,
+    connection: Connection,
+}
+
+/// Watcher of the clipboard contents.
+///
+/// Returned by [`watch()`]. It's an iterator which blocks until the clipboard contents change, and
+/// yields the [`Offer`] of the new contents, or `None` if the clipboard was cleared. The first
+/// item describes the clipboard contents at the time the watcher was created.
+///
+/// The iteration ends once the seat goes away, or after an error.
+pub struct Watcher {
+    queue: EventQueue<State>,
+    state: State,
+    connection: Connection,
+    seat: WlSeat,
+    primary: bool,
+    // The last yielded offer, `None` before the first one.
+    last: Option<Option<data_control::Offer>>,
+    failed: bool,
+}
+
+pub(crate) struct State {
+    pub(crate) common: common::State,
+    // The value is the list of MIME types in the offer, in the order they were offered.
+    pub(crate) offers: HashMap<data_control::Offer, Vec<String>>,
+    pub(crate) got_primary_selection: bool,
+}
+
+impl State {
+    // Creates the data devices for all seats.
+    pub(crate) fn new(mut common: common::State, qh: &QueueHandle<Self>) -> Self {
+        for (seat, data) in &mut common.seats {
+            let device = common
+                .clipboard_manager
+                .get_data_device(seat, qh, seat.clone());
+            data.set_device(Some(device));
+        }
+
+        Self {
+            common,
+            offers: HashMap::new(),
+            got_primary_selection: false,
+        }
+    }
+
+    // Destroys the offers that aren't the selection of any seat, such as the offers that never
+    // became one, so that they don't pile up in the long-lived connections.
+    fn remove_stale_offers(&mut self) {
+        self.offers.retain(|offer, _| {
+            let current = self.common.seats.values().any(|data| {
+                data.offer.as_ref() == Some(offer) || data.primary_offer.as_ref() == Some(offer)
+            });
+            if !current {
+                offer.destroy();
+            }
+            current
+        });
+    }
+
+    // Figures out which seat we're interested in.
+    pub(crate) fn find_seat(&self, primary: bool, seat: Seat<'_>) -> Result<WlSeat, Error> {
+        // Check if there are no seats.
+        if self.common.seats.is_empty() {
+            return Err(Error::NoSeats);
+        }
+
+        // Check if the compositor supports primary selection.
+        if primary && !self.got_primary_selection {
+            return Err(Error::PrimarySelectionUnsupported);
+        }
+
+        let seat = match seat {
+            Seat::Unspecified => self.common.seats.keys().next(),
+            Seat::Specific(name) => self
+                .common
+                .seats
+                .iter()
+                .find(|(_, data)| data.name.as_deref() == Some(name))
+                .map(|(seat, _)| seat),
+        };
+
+        seat.cloned().ok_or(Error::SeatNotFound)
+    }
+
+    // Figures out which offer we're interested in.
+    pub(crate) fn find_offer(
+        &self,
+        primary: bool,
+        seat: &WlSeat,
+    ) -> Result<data_control::Offer, Error> {
+        let data = &self.common.seats[seat];
+        let offer = if primary {
+            &data.primary_offer
+        } else {
+            &data.offer
+        };
+
+        // Check if we found anything.
+        offer.clone().ok_or(Error::ClipboardEmpty)
+    }
 }
 
 delegate_dispatch!(State: [WlSeat: ()] => common::State);
@@ -108,10 +260,10 @@ pub enum Error {
     WaylandCommunication(#[source] DispatchError),
 
     #[error(
-        "A required Wayland protocol (ext-data-control, or wlr-data-control version {version}) \
-         is not supported by the compositor"
+        "A required Wayland protocol ({}) is not supported by the compositor",
+        describe_protocol(*.protocol, *.version)
     )]
-    MissingProtocol { version: u32 },
+    MissingProtocol { protocol: Protocol, version: u32 },
 
     #[error("The compositor does not support primary selection")]
     PrimarySelectionUnsupported,
@@ -121,6 +273,18 @@ pub enum Error {
 
     #[error("Couldn't create a pipe for content transfer")]
     PipeCreation(#[source] io::Error),
+
+    #[error("Couldn't register the pipe with the async reactor")]
+    AsyncRegistration(#[source] io::Error),
+
+    #[error("Couldn't read the clipboard contents")]
+    PipeRead(#[source] io::Error),
+
+    #[error("Timed out while pasting")]
+    Timeout,
+
+    #[error("The clipboard contents are larger than {max_size} bytes")]
+    TooLarge { max_size: u64 },
 }
 
 impl From<common::Error> for Error {
@@ -131,7 +295,7 @@ impl From<common::Error> for Error {
             SocketOpenError(err) => Error::SocketOpenError(err),
             WaylandConnection(err) => Error::WaylandConnection(err),
             WaylandCommunication(err) => Error::WaylandCommunication(err.into()),
-            MissingProtocol { version } => Error::MissingProtocol { version },
+            MissingProtocol { protocol, version } => Error::MissingProtocol { protocol, version },
         }
     }
 }
@@ -148,18 +312,37 @@ impl Dispatch<WlRegistry, GlobalListContents> for State {
     }
 }
 
+impl Dispatch<WlCallback, Arc<AtomicBool>> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlCallback,
+        event: <WlCallback as wayland_client::Proxy>::Event,
+        done: &Arc<AtomicBool>,
+        _conn: &wayland_client::Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            done.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
 impl_dispatch_manager!(State);
 
 impl_dispatch_device!(State, WlSeat, |state: &mut Self, event, seat| {
     match event {
         Event::DataOffer { id } => {
             let offer = data_control::Offer::from(id);
-            state.offers.insert(offer, HashSet::new());
+            state.offers.insert(offer, Vec::new());
         }
         Event::Selection { id } => {
             let offer = id.map(data_control::Offer::from);
             let seat = state.common.seats.get_mut(seat).unwrap();
+            if let Some(old_offer) = &seat.offer {
+                state.offers.remove(old_offer);
+            }
             seat.set_offer(offer);
+            state.remove_stale_offers();
         }
         Event::Finished => {
             // Destroy the device stored in the seat as it's no longer valid.
@@ -170,7 +353,11 @@ impl_dispatch_device!(State, WlSeat, |state: &mut Self, event, seat| {
             let offer = id.map(data_control::Offer::from);
             state.got_primary_selection = true;
             let seat = state.common.seats.get_mut(seat).unwrap();
+            if let Some(old_offer) = &seat.primary_offer {
+                state.offers.remove(old_offer);
+            }
             seat.set_primary_offer(offer);
+            state.remove_stale_offers();
         }
         _ => (),
     }
@@ -180,70 +367,408 @@ impl_dispatch_offer!(State, |state: &mut Self,
                              offer: data_control::Offer,
                              event| {
     if let Event::Offer { mime_type } = event {
-        state.offers.get_mut(&offer).unwrap().insert(mime_type);
+        let mime_types = state.offers.get_mut(&offer).unwrap();
+        if !mime_types.contains(&mime_type) {
+            mime_types.push(mime_type);
+        }
     }
 });
 
-fn get_offer(
+// Finds the desired MIME type among the offered ones.
+//
+// Where several MIME types fit equally well, the one offered first wins.
+pub(crate) fn select_mime_type(mime_types: &[String], mime_type: MimeType<'_>) -> Option<String> {
+    let find = |mime_type: &str| mime_types.iter().find(|x| *x == mime_type);
+    let find_text = || mime_types.iter().find(|x| is_text(x));
+
+    match mime_type {
+        MimeType::Any => find("text/plain;charset=utf-8")
+            .or_else(|| find("UTF8_STRING"))
+            .or_else(find_text)
+            .or_else(|| mime_types.first()),
+        MimeType::Text => find("text/plain;charset=utf-8")
+            .or_else(|| find("UTF8_STRING"))
+            .or_else(find_text),
+        MimeType::TextWithPriority(priority) => find(priority)
+            .or_else(|| find("text/plain;charset=utf-8"))
+            .or_else(|| find("UTF8_STRING"))
+            .or_else(find_text),
+        MimeType::Specific(mime_type) => find(mime_type),
+        MimeType::Preferences(patterns) => patterns
+            .iter()
+            .find_map(|pattern| mime_types.iter().find(|x| mime_type_matches(pattern, x))),
+    }
+    .cloned()
+}
+
+// Performs a roundtrip like EventQueue::roundtrip(), giving up at the deadline.
+//
+// Returns `false` if the deadline passed. Unlike EventQueue::roundtrip(), this doesn't get stuck
+// when another event queue on the same connection reads the events of this queue.
+pub(crate) fn roundtrip_until(
+    conn: &Connection,
+    queue: &mut EventQueue<State>,
+    state: &mut State,
+    deadline: Option<Instant>,
+) -> Result<bool, DispatchError> {
+    let done = Arc::new(AtomicBool::new(false));
+    conn.display().sync(&queue.handle(), done.clone());
+    queue.flush()?;
+
+    loop {
+        queue.dispatch_pending(state)?;
+
+        if done.load(Ordering::Relaxed) {
+            return Ok(true);
+        }
+
+        let Some(guard) = queue.prepare_read() else {
+            continue;
+        };
+
+        // Another event queue may have read our events right before the read was prepared.
+        if queue.dispatch_pending(state)? > 0 {
+            continue;
+        }
+
+        {
+            let fd = guard.connection_fd();
+            let mut fds = [PollFd::new(&fd, PollFlags::IN)];
+            match poll(&mut fds, poll_timeout(deadline)) {
+                Ok(0) => return Ok(false),
+                Ok(_) => (),
+                Err(Errno::INTR) => continue,
+                Err(err) => return Err(WaylandError::Io(err.into()).into()),
+            }
+        }
+
+        read_events(guard)?;
+    }
+}
+
+fn roundtrip(
+    conn: &Connection,
+    queue: &mut EventQueue<State>,
+    state: &mut State,
+    deadline: Option<Instant>,
+) -> Result<(), Error> {
+    match roundtrip_until(conn, queue, state, deadline) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::Timeout),
+        Err(err) => Err(Error::WaylandCommunication(err)),
+    }
+}
+
+fn get_seat(
     primary: bool,
     seat: Seat<'_>,
+    protocol: Protocol,
     socket_name: Option<OsString>,
-) -> Result<(EventQueue<State>, State, data_control::Offer), Error> {
-    let (mut queue, mut common) = initialize(primary, socket_name)?;
+    deadline: Option<Instant>,
+) -> Result<(Connection, EventQueue<State>, State, WlSeat), Error> {
+    let conn = connect(socket_name)?;
+    let (mut queue, common) = initialize_with(&conn, protocol, wlr_versions(primary))?;
 
     // Check if there are no seats.
     if common.seats.is_empty() {
         return Err(Error::NoSeats);
     }
 
-    // Go through the seats and get their data devices.
-    for (seat, data) in &mut common.seats {
-        let device = common
-            .clipboard_manager
-            .get_data_device(seat, &queue.handle(), seat.clone());
-        data.set_device(Some(device));
-    }
-
-    let mut state = State {
-        common,
-        offers: HashMap::new(),
-        got_primary_selection: false,
-    };
+    let mut state = State::new(common, &queue.handle());
 
     // Retrieve all seat names and offers.
-    queue
-        .roundtrip(&mut state)
-        .map_err(Error::WaylandCommunication)?;
-
-    // Check if the compositor supports primary selection.
-    if primary && !state.got_primary_selection {
-        return Err(Error::PrimarySelectionUnsupported);
-    }
-
-    // Figure out which offer we're interested in.
-    let data = match seat {
-        Seat::Unspecified => state.common.seats.values().next(),
-        Seat::Specific(name) => state
-            .common
-            .seats
-            .values()
-            .find(|data| data.name.as_deref() == Some(name)),
-    };
+    roundtrip(&conn, &mut queue, &mut state, deadline)?;
 
-    let Some(data) = data else {
-        return Err(Error::SeatNotFound);
-    };
+    let seat = state.find_seat(primary, seat)?;
+    Ok((conn, queue, state, seat))
+}
+
+fn get_offer(
+    primary: bool,
+    seat: Seat<'_>,
+    protocol: Protocol,
+    socket_name: Option<OsString>,
+    deadline: Option<Instant>,
+) -> Result<(Connection, EventQueue<State>, State, data_control::Offer), Error> {
+    let (conn, queue, state, seat) = get_seat(primary, seat, protocol, socket_name, deadline)?;
+    let offer = state.find_offer(primary, &seat)?;
+    Ok((conn, queue, state, offer))
+}
+
+// Starts the transfer of the contents, returning the pipe and the actual MIME type.
+fn receive(
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    mime_type: MimeType<'_>,
+    protocol: Protocol,
+    socket_name: Option<OsString>,
+    deadline: Option<Instant>,
+) -> Result<(PipeReader, String), Error> {
+    let primary = clipboard == ClipboardType::Primary;
+    let (conn, mut queue, mut state, offer) =
+        get_offer(primary, seat, protocol, socket_name, deadline)?;
+
+    let (read, mime_type) = start_transfer(&mut state, &offer, mime_type)?;
+
+    // A flush() is not enough here, it will result in sometimes pasting empty contents. I suspect this is due to a
+    // race between the compositor reacting to the receive request, and the compositor reacting to wl-paste
+    // disconnecting after queue is dropped. The roundtrip solves that race.
+    roundtrip(&conn, &mut queue, &mut state, deadline)?;
+
+    Ok((read, mime_type))
+}
+
+// Requests the offered contents in a suitable MIME type, returning the pipe and the MIME type.
+//
+// The request still has to reach the compositor.
+fn start_transfer(
+    state: &mut State,
+    offer: &data_control::Offer,
+    mime_type: MimeType<'_>,
+) -> Result<(PipeReader, String), Error> {
+    let mime_types = state.offers.remove(offer).unwrap();
 
-    let offer = if primary {
-        &data.primary_offer
-    } else {
-        &data.offer
+    // Check if a suitable MIME type is copied.
+    let Some(mime_type) = select_mime_type(&mime_types, mime_type) else {
+        return Err(Error::NoMimeType);
     };
 
-    // Check if we found anything.
-    match offer.clone() {
-        Some(offer) => Ok((queue, state, offer)),
-        None => Err(Error::ClipboardEmpty),
+    // Create a pipe for content transfer.
+    let (read, write) = pipe().map_err(Error::PipeCreation)?;
+
+    // Start the transfer.
+    offer.receive(mime_type.clone(), write.as_fd());
+    drop(write);
+
+    Ok((read, mime_type))
+}
+
+// Reads the contents from the pipe, giving up at the deadline or once they exceed the maximum size.
+pub(crate) fn read_pipe(
+    mut read: PipeReader,
+    max_size: Option<u64>,
+    deadline: Option<Instant>,
+) -> Result<Vec<u8>, Error> {
+    let mut contents = vec![];
+    let mut buf = [0; 8192];
+
+    loop {
+        let mut fds = [PollFd::new(&read, PollFlags::IN)];
+        match poll(&mut fds, poll_timeout(deadline)) {
+            Ok(0) => return Err(Error::Timeout),
+            Ok(_) => (),
+            // Poll again rather than risk a read that blocks past the deadline.
+            Err(Errno::INTR) => continue,
+            Err(err) => return Err(Error::PipeRead(err.into())),
+        }
+
+        let count = match read.read(&mut buf) {
+            Ok(0) => return Ok(contents),
+            Ok(count) => count,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::PipeRead(err)),
+        };
+
+        contents.extend_from_slice(&buf[..count]);
+
+        if let Some(max_size) = max_size {
+            if contents.len() as u64 > max_size {
+                return Err(Error::TooLarge { max_size });
+            }
+        }
+    }
+}
+
+impl Options {
+    /// Creates a blank new set of options ready for configuration.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the time limit for pasting.
+    ///
+    /// The limit covers both the exchange with the compositor and the transfer of the contents
+    /// from the client that copied them, which may never finish if that client misbehaves.
+    #[inline]
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum size of the contents in bytes.
+    ///
+    /// When pasting all MIME types, the limit applies to each of them separately.
+    #[inline]
+    pub fn max_size(&mut self, max_size: u64) -> &mut Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sets the MIME types to paste when pasting all MIME types.
+    ///
+    /// The other offered MIME types are skipped. By default all of them are pasted.
+    #[inline]
+    pub fn allow_mime_types(&mut self, mime_types: HashSet<String>) -> &mut Self {
+        self.allowed_mime_types = Some(mime_types);
+        self
+    }
+
+    /// Sets the MIME types to skip when pasting all MIME types.
+    ///
+    /// This takes precedence over [`allow_mime_types()`](Self::allow_mime_types).
+    #[inline]
+    pub fn deny_mime_types(&mut self, mime_types: HashSet<String>) -> &mut Self {
+        self.denied_mime_types = mime_types;
+        self
+    }
+
+    /// Sets the data-control protocol to use.
+    ///
+    /// See [`Protocol`].
+    #[inline]
+    pub fn protocol(&mut self, protocol: Protocol) -> &mut Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Sets the Wayland display to connect to.
+    ///
+    /// This is either a display name relative to `XDG_RUNTIME_DIR`, like `wayland-1`, or an
+    /// absolute path to the compositor socket. By default the compositor is found through the
+    /// `WAYLAND_SOCKET` and `WAYLAND_DISPLAY` environment variables.
+    ///
+    /// To use an existing connection, see
+    /// [`Clipboard::from_connection()`](crate::clipboard::Clipboard::from_connection).
+    #[inline]
+    pub fn display(&mut self, display: impl Into<OsString>) -> &mut Self {
+        self.display = Some(display.into());
+        self
+    }
+
+    // Checks whether the MIME type passes the allow and deny lists.
+    fn is_mime_type_allowed(&self, mime_type: &str) -> bool {
+        !self.denied_mime_types.contains(mime_type)
+            && self
+                .allowed_mime_types
+                .as_ref()
+                .is_none_or(|allowed| allowed.contains(mime_type))
+    }
+
+    /// Invokes the read_contents operation. See `read_contents()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate wl_clipboard_rs;
+    /// # use wl_clipboard_rs::paste::Error;
+    /// # fn foo() -> Result<(), Error> {
+    /// use std::time::Duration;
+    /// use wl_clipboard_rs::paste::{ClipboardType, MimeType, Options, Seat};
+    ///
+    /// let mut opts = Options::new();
+    /// opts.timeout(Duration::from_secs(5)).max_size(1 << 20);
+    /// let (contents, mime_type) =
+    ///     opts.read_contents(ClipboardType::Regular, Seat::Unspecified, MimeType::Any)?;
+    /// println!("Read {} bytes of {} data", contents.len(), mime_type);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn read_contents(
+        self,
+        clipboard: ClipboardType,
+        seat: Seat<'_>,
+        mime_type: MimeType<'_>,
+    ) -> Result<(Vec<u8>, String), Error> {
+        read_contents(self, clipboard, seat, mime_type)
+    }
+
+    /// Invokes the get_all_contents operation. See `get_all_contents()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate wl_clipboard_rs;
+    /// # use wl_clipboard_rs::paste::Error;
+    /// # fn foo() -> Result<(), Error> {
+    /// use std::collections::HashSet;
+    /// use wl_clipboard_rs::paste::{ClipboardType, Options, Seat};
+    ///
+    /// let mut opts = Options::new();
+    /// opts.max_size(1 << 20)
+    ///     .deny_mime_types(HashSet::from(["image/png".to_owned()]));
+    /// let contents = opts.get_all_contents(ClipboardType::Regular, Seat::Unspecified)?;
+    /// for (mime_type, data) in contents {
+    ///     println!("{}: {} bytes", mime_type, data.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn get_all_contents(
+        self,
+        clipboard: ClipboardType,
+        seat: Seat<'_>,
+    ) -> Result<HashMap<String, Vec<u8>>, Error> {
+        get_all_contents_internal(self, clipboard, seat, None)
+    }
+
+    /// Invokes the get_contents operation. See `get_contents()`.
+    ///
+    /// The timeout covers only the exchange with the compositor, since the contents are read from
+    /// the returned pipe by the caller. The maximum size and the MIME type lists are not used.
+    #[inline]
+    pub fn get_contents(
+        self,
+        clipboard: ClipboardType,
+        seat: Seat<'_>,
+        mime_type: MimeType<'_>,
+    ) -> Result<(PipeReader, String), Error> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        receive(
+            clipboard,
+            seat,
+            mime_type,
+            self.protocol,
+            self.display,
+            deadline,
+        )
+    }
+
+    /// Invokes the get_mime_types operation. See `get_mime_types()`.
+    ///
+    /// The maximum size and the MIME type lists are not used.
+    #[inline]
+    pub fn get_mime_types(
+        self,
+        clipboard: ClipboardType,
+        seat: Seat<'_>,
+    ) -> Result<HashSet<String>, Error> {
+        let mime_types = self.get_mime_types_ordered(clipboard, seat)?;
+        Ok(mime_types.into_iter().collect())
+    }
+
+    /// Invokes the get_mime_types_ordered operation. See `get_mime_types_ordered()`.
+    ///
+    /// The maximum size and the MIME type lists are not used.
+    #[inline]
+    pub fn get_mime_types_ordered(
+        self,
+        clipboard: ClipboardType,
+        seat: Seat<'_>,
+    ) -> Result<Vec<String>, Error> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        mime_types(clipboard, seat, self.protocol, self.display, deadline)
+    }
+
+    /// Invokes the watch operation. See `watch()`.
+    ///
+    /// Only the protocol and the display are used, pass the options to [`Offer::receive_all()`]
+    /// for the rest.
+    #[inline]
+    pub fn watch(self, clipboard: ClipboardType, seat: Seat<'_>) -> Result<Watcher, Error> {
+        watch_with_protocol(clipboard, seat, self.protocol, self.display)
     }
 }
 
@@ -279,8 +804,58 @@ pub(crate) fn get_mime_types_internal(
     seat: Seat<'_>,
     socket_name: Option<OsString>,
 ) -> Result<HashSet<String>, Error> {
+    let mime_types = get_mime_types_ordered_internal(clipboard, seat, socket_name)?;
+    Ok(mime_types.into_iter().collect())
+}
+
+/// Retrieves the offered MIME types in the order they were offered.
+///
+/// The order usually reflects the preference of the client that copied the contents, for
+/// example with a rich format coming before plain text. Otherwise this is the same as
+/// [`get_mime_types()`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # extern crate wl_clipboard_rs;
+/// # use wl_clipboard_rs::paste::Error;
+/// # fn foo() -> Result<(), Error> {
+/// use wl_clipboard_rs::{paste::{get_mime_types_ordered, ClipboardType, Seat}};
+///
+/// let mime_types = get_mime_types_ordered(ClipboardType::Regular, Seat::Unspecified)?;
+/// if let Some(preferred) = mime_types.first() {
+///     println!("Preferred MIME type: {}", preferred);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[inline]
+pub fn get_mime_types_ordered(
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+) -> Result<Vec<String>, Error> {
+    get_mime_types_ordered_internal(clipboard, seat, None)
+}
+
+// The internal function accepts the socket name, used for tests.
+pub(crate) fn get_mime_types_ordered_internal(
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    socket_name: Option<OsString>,
+) -> Result<Vec<String>, Error> {
+    mime_types(clipboard, seat, Protocol::Auto, socket_name, None)
+}
+
+// Retrieves the offered MIME types in the order they were offered.
+fn mime_types(
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    protocol: Protocol,
+    socket_name: Option<OsString>,
+    deadline: Option<Instant>,
+) -> Result<Vec<String>, Error> {
     let primary = clipboard == ClipboardType::Primary;
-    let (_, mut state, offer) = get_offer(primary, seat, socket_name)?;
+    let (_, _, mut state, offer) = get_offer(primary, seat, protocol, socket_name, deadline)?;
     Ok(state.offers.remove(&offer).unwrap())
 }
 
@@ -336,50 +911,515 @@ pub(crate) fn get_contents_internal(
     mime_type: MimeType<'_>,
     socket_name: Option<OsString>,
 ) -> Result<(PipeReader, String), Error> {
+    receive(
+        clipboard,
+        seat,
+        mime_type,
+        Protocol::Auto,
+        socket_name,
+        None,
+    )
+}
+
+/// Reads the clipboard contents, subject to the limits in `options`.
+///
+/// Unlike [`get_contents()`], which hands out the pipe, this function reads the contents itself
+/// and returns them along with their actual MIME type. This way it can enforce the limits: it
+/// fails with [`Error::Timeout`] if pasting takes longer than the timeout, and with
+/// [`Error::TooLarge`] if the contents exceed the maximum size.
+///
+/// If `seat` is `None`, uses an unspecified seat (it depends on the order returned by the
+/// compositor). This is perfectly fine when only a single seat is present, so for most
+/// configurations.
+///
+/// # Examples
+///
+/// ```no_run
+/// # extern crate wl_clipboard_rs;
+/// # use wl_clipboard_rs::paste::Error;
+/// # fn foo() -> Result<(), Error> {
+/// use std::time::Duration;
+/// use wl_clipboard_rs::paste::{read_contents, ClipboardType, MimeType, Options, Seat};
+///
+/// let mut opts = Options::new();
+/// opts.timeout(Duration::from_secs(5));
+/// let (contents, mime_type) =
+///     read_contents(opts, ClipboardType::Regular, Seat::Unspecified, MimeType::Any)?;
+/// println!("Read {} bytes of {} data", contents.len(), mime_type);
+/// # Ok(())
+/// # }
+/// ```
+#[inline]
+pub fn read_contents(
+    options: Options,
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    mime_type: MimeType<'_>,
+) -> Result<(Vec<u8>, String), Error> {
+    read_contents_internal(options, clipboard, seat, mime_type, None)
+}
+
+// The internal function accepts the socket name, used for tests.
+pub(crate) fn read_contents_internal(
+    options: Options,
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    mime_type: MimeType<'_>,
+    socket_name: Option<OsString>,
+) -> Result<(Vec<u8>, String), Error> {
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let (read, mime_type) = receive(
+        clipboard,
+        seat,
+        mime_type,
+        options.protocol,
+        socket_name.or_else(|| options.display.clone()),
+        deadline,
+    )?;
+    let contents = read_pipe(read, options.max_size, deadline)?;
+    Ok((contents, mime_type))
+}
+
+/// Retrieves the clipboard contents in all offered MIME types.
+///
+/// All MIME types are received from the same offer over a single connection, so the result is a
+/// consistent snapshot of the clipboard, unless the contents change while it's being taken. The
+/// returned map goes from the MIME type to the contents.
+///
+/// This function uses the default options, use [`Options::get_all_contents()`] for limiting the
+/// size of the contents or the MIME types to paste.
+///
+/// If `seat` is `None`, uses an unspecified seat (it depends on the order returned by the
+/// compositor). This is perfectly fine when only a single seat is present, so for most
+/// configurations.
+///
+/// # Examples
+///
+/// ```no_run
+/// # extern crate wl_clipboard_rs;
+/// # use wl_clipboard_rs::paste::Error;
+/// # fn foo() -> Result<(), Error> {
+/// use wl_clipboard_rs::paste::{get_all_contents, ClipboardType, Seat};
+///
+/// let contents = get_all_contents(ClipboardType::Regular, Seat::Unspecified)?;
+/// for (mime_type, data) in contents {
+///     println!("{}: {} bytes", mime_type, data.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[inline]
+pub fn get_all_contents(
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+) -> Result<HashMap<String, Vec<u8>>, Error> {
+    get_all_contents_internal(Options::default(), clipboard, seat, None)
+}
+
+// The internal function accepts the socket name, used for tests.
+pub(crate) fn get_all_contents_internal(
+    options: Options,
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    socket_name: Option<OsString>,
+) -> Result<HashMap<String, Vec<u8>>, Error> {
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
     let primary = clipboard == ClipboardType::Primary;
-    let (mut queue, mut state, offer) = get_offer(primary, seat, socket_name)?;
-
-    let mut mime_types = state.offers.remove(&offer).unwrap();
-
-    // Find the desired MIME type.
-    let mime_type = match mime_type {
-        MimeType::Any => mime_types
-            .take("text/plain;charset=utf-8")
-            .or_else(|| mime_types.take("UTF8_STRING"))
-            .or_else(|| mime_types.iter().find(|x| is_text(x)).cloned())
-            .or_else(|| mime_types.drain().next()),
-        MimeType::Text => mime_types
-            .take("text/plain;charset=utf-8")
-            .or_else(|| mime_types.take("UTF8_STRING"))
-            .or_else(|| mime_types.drain().find(|x| is_text(x))),
-        MimeType::TextWithPriority(priority) => mime_types
-            .take(priority)
-            .or_else(|| mime_types.take("text/plain;charset=utf-8"))
-            .or_else(|| mime_types.take("UTF8_STRING"))
-            .or_else(|| mime_types.drain().find(|x| is_text(x))),
-        MimeType::Specific(mime_type) => mime_types.take(mime_type),
-    };
+    let socket_name = socket_name.or_else(|| options.display.clone());
+    let (conn, _queue, mut state, offer) =
+        get_offer(primary, seat, options.protocol, socket_name, deadline)?;
 
-    // Check if a suitable MIME type is copied.
-    if mime_type.is_none() {
-        return Err(Error::NoMimeType);
+    let mime_types = state.offers.remove(&offer).unwrap();
+    receive_all(&conn, &offer, mime_types, &options, deadline)
+}
+
+// Receives the offered contents in all MIME types allowed by the options.
+fn receive_all(
+    conn: &Connection,
+    offer: &data_control::Offer,
+    mime_types: Vec<String>,
+    options: &Options,
+    deadline: Option<Instant>,
+) -> Result<HashMap<String, Vec<u8>>, Error> {
+    let mut contents = HashMap::new();
+    for mime_type in mime_types {
+        if !options.is_mime_type_allowed(&mime_type) {
+            continue;
+        }
+
+        // Create a pipe for content transfer.
+        let (read, write) = pipe().map_err(Error::PipeCreation)?;
+
+        // Receive one MIME type at a time: the client may serve the requests one by one, blocking
+        // on a full pipe that we wouldn't read from yet.
+        offer.receive(mime_type.clone(), write.as_fd());
+        drop(write);
+
+        conn.flush()
+            .map_err(|err| Error::WaylandCommunication(err.into()))?;
+
+        let data = read_pipe(read, options.max_size, deadline)?;
+        contents.insert(mime_type, data);
     }
 
-    let mime_type = mime_type.unwrap();
+    Ok(contents)
+}
 
-    // Create a pipe for content transfer.
-    let (read, write) = pipe().map_err(Error::PipeCreation)?;
+/// Watches the clipboard contents for changes.
+///
+/// Returns a [`Watcher`], which is an iterator over the clipboard contents. It yields the current
+/// contents first, and then the new contents every time they change. The data-control device is
+/// kept alive for as long as the watcher is, so there's no need to poll the clipboard.
+///
+/// If `seat` is `None`, uses an unspecified seat (it depends on the order returned by the
+/// compositor). This is perfectly fine when only a single seat is present, so for most
+/// configurations.
+///
+/// # Examples
+///
+/// ```no_run
+/// # extern crate wl_clipboard_rs;
+/// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Read;
+/// use wl_clipboard_rs::paste::{watch, ClipboardType, MimeType, Seat};
+///
+/// for offer in watch(ClipboardType::Regular, Seat::Unspecified)? {
+///     let Some(offer) = offer? else {
+///         println!("The clipboard was cleared");
+///         continue;
+///     };
+///
+///     let (mut pipe, mime_type) = offer.receive(MimeType::Any)?;
+///     let mut contents = vec![];
+///     pipe.read_to_end(&mut contents)?;
+///     println!("Copied {} bytes of {} data", contents.len(), mime_type);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[inline]
+pub fn watch(clipboard: ClipboardType, seat: Seat<'_>) -> Result<Watcher, Error> {
+    watch_internal(clipboard, seat, None)
+}
 
-    // Start the transfer.
-    offer.receive(mime_type.clone(), write.as_fd());
-    drop(write);
+// The internal function accepts the socket name, used for tests.
+pub(crate) fn watch_internal(
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    socket_name: Option<OsString>,
+) -> Result<Watcher, Error> {
+    watch_with_protocol(clipboard, seat, Protocol::Auto, socket_name)
+}
 
-    // A flush() is not enough here, it will result in sometimes pasting empty contents. I suspect this is due to a
-    // race between the compositor reacting to the receive request, and the compositor reacting to wl-paste
-    // disconnecting after queue is dropped. The roundtrip solves that race.
-    queue
-        .roundtrip(&mut state)
-        .map_err(Error::WaylandCommunication)?;
+pub(crate) fn watch_with_protocol(
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    protocol: Protocol,
+    socket_name: Option<OsString>,
+) -> Result<Watcher, Error> {
+    let primary = clipboard == ClipboardType::Primary;
+    // The connection is needed for flushing the receive requests of the yielded offers.
+    let (connection, queue, state, seat) = get_seat(primary, seat, protocol, socket_name, None)?;
+
+    Ok(Watcher {
+        queue,
+        state,
+        connection,
+        seat,
+        primary,
+        last: None,
+        failed: false,
+    })
+}
+
+impl Watcher {
+    /// Calls `callback` every time the clipboard contents change.
+    ///
+    /// This is the callback form of iterating over the watcher: `callback` receives the same
+    /// items, and watching continues until it returns [`ControlFlow::Break`] or an error occurs.
+    pub fn run(
+        self,
+        mut callback: impl FnMut(Option<Offer>) -> ControlFlow<()>,
+    ) -> Result<(), Error> {
+        for offer in self {
+            if callback(offer?).is_break() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Iterator for Watcher {
+    type Item = Result<Option<Offer>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            let data = &self.state.common.seats[&self.seat];
+
+            // The device is gone along with the seat.
+            data.device.as_ref()?;
+
+            let offer = if self.primary {
+                &data.primary_offer
+            } else {
+                &data.offer
+            };
+
+            if self.last.as_ref() != Some(offer) {
+                self.last = Some(offer.clone());
+
+                let offer = offer.clone().map(|offer| Offer {
+                    mime_types: self.state.offers.get(&offer).cloned().unwrap_or_default(),
+                    offer,
+                    connection: self.connection.clone(),
+                });
+                return Some(Ok(offer));
+            }
+
+            if let Err(err) = self.queue.blocking_dispatch(&mut self.state) {
+                self.failed = true;
+                return Some(Err(Error::WaylandCommunication(err)));
+            }
+        }
+    }
+}
+
+impl Offer {
+    /// Returns the offered MIME types in the order they were offered.
+    pub fn mime_types(&self) -> &[String] {
+        &self.mime_types
+    }
+
+    /// Receives the offered contents.
+    ///
+    /// This function returns a tuple of the reading end of a pipe containing the contents and the
+    /// actual MIME type of the contents, like [`get_contents()`].
+    pub fn receive(&self, mime_type: MimeType<'_>) -> Result<(PipeReader, String), Error> {
+        let Some(mime_type) = select_mime_type(&self.mime_types, mime_type) else {
+            return Err(Error::NoMimeType);
+        };
+
+        // Create a pipe for content transfer.
+        let (read, write) = pipe().map_err(Error::PipeCreation)?;
+
+        // Start the transfer.
+        self.offer.receive(mime_type.clone(), write.as_fd());
+        drop(write);
+
+        self.connection
+            .flush()
+            .map_err(|err| Error::WaylandCommunication(err.into()))?;
+
+        Ok((read, mime_type))
+    }
+
+    /// Receives the offered contents in all MIME types.
+    ///
+    /// This is the [`Offer`] counterpart of [`Options::get_all_contents()`], subject to the same
+    /// options.
+    pub fn receive_all(&self, options: Options) -> Result<HashMap<String, Vec<u8>>, Error> {
+        let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+        receive_all(
+            &self.connection,
+            &self.offer,
+            self.mime_types.clone(),
+            &options,
+            deadline,
+        )
+    }
+}
+
+/// Retrieves the clipboard contents asynchronously.
+///
+/// This is the async version of [`get_contents()`]. It returns a tuple of the reading end of a
+/// pipe containing the clipboard contents, which implements `AsyncRead`, and the actual MIME type
+/// of the contents. It works with any async runtime.
+///
+/// Waiting for the compositor and reading the contents both yield to the executor. Only
+/// connecting to the compositor socket happens synchronously.
+///
+/// # Examples
+///
+/// ```no_run
+/// # extern crate wl_clipboard_rs;
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// use futures_lite::AsyncReadExt;
+/// use wl_clipboard_rs::paste::{get_contents_async, ClipboardType, MimeType, Seat};
+///
+/// let (mut pipe, mime_type) =
+///     get_contents_async(ClipboardType::Regular, Seat::Unspecified, MimeType::Any).await?;
+///
+/// let mut contents = vec![];
+/// pipe.read_to_end(&mut contents).await?;
+/// println!("Read {} bytes of {} data", contents.len(), mime_type);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "async")]
+#[inline]
+pub async fn get_contents_async(
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    mime_type: MimeType<'_>,
+) -> Result<(async_io::Async<File>, String), Error> {
+    get_contents_async_internal(clipboard, seat, mime_type, None).await
+}
+
+// The internal function accepts the socket name, used for tests.
+#[cfg(feature = "async")]
+pub(crate) async fn get_contents_async_internal(
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    mime_type: MimeType<'_>,
+    socket_name: Option<OsString>,
+) -> Result<(async_io::Async<File>, String), Error> {
+    let primary = clipboard == ClipboardType::Primary;
+    let conn = connect(socket_name)?;
+
+    // Register a duplicate of the connection fd for waiting on it.
+    let fd = conn
+        .backend()
+        .poll_fd()
+        .try_clone_to_owned()
+        .and_then(async_io::Async::new)
+        .map_err(Error::AsyncRegistration)?;
+
+    // Retrieve the global interfaces. The state needs them first, so they go to a queue of their
+    // own.
+    let mut globals_queue = conn.new_event_queue();
+    let registry = conn.display().get_registry(&globals_queue.handle(), ());
+    let mut globals = Globals(Vec::new());
+    roundtrip_async(&conn, &mut globals_queue, &mut globals, &fd).await?;
+
+    let mut queue = conn.new_event_queue();
+    let common = common::bind_globals(
+        &registry,
+        &globals.0,
+        &queue.handle(),
+        Protocol::Auto,
+        wlr_versions(primary),
+    )?;
+
+    // Check if there are no seats.
+    if common.seats.is_empty() {
+        return Err(Error::NoSeats);
+    }
+
+    let mut state = State::new(common, &queue.handle());
+
+    // Retrieve all seat names and offers.
+    roundtrip_async(&conn, &mut queue, &mut state, &fd).await?;
+
+    let seat = state.find_seat(primary, seat)?;
+    let offer = state.find_offer(primary, &seat)?;
+    let (read, mime_type) = start_transfer(&mut state, &offer, mime_type)?;
+
+    // See receive() for why this is not just a flush().
+    roundtrip_async(&conn, &mut queue, &mut state, &fd).await?;
+
+    let read =
+        async_io::Async::new(File::from(OwnedFd::from(read))).map_err(Error::AsyncRegistration)?;
 
     Ok((read, mime_type))
 }
+
+// The globals advertised by the compositor.
+#[cfg(feature = "async")]
+struct Globals(Vec<Global>);
+
+#[cfg(feature = "async")]
+impl Dispatch<WlRegistry, ()> for Globals {
+    fn event(
+        state: &mut Self,
+        _registry: &WlRegistry,
+        event: <WlRegistry as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &wayland_client::Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } => state.0.push(Global {
+                name,
+                interface,
+                version,
+            }),
+            wl_registry::Event::GlobalRemove { name } => {
+                state.0.retain(|global| global.name != name)
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Dispatch<WlCallback, Arc<AtomicBool>> for Globals {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlCallback,
+        event: <WlCallback as wayland_client::Proxy>::Event,
+        done: &Arc<AtomicBool>,
+        _conn: &wayland_client::Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            done.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+// Performs a roundtrip like roundtrip_until(), waiting for the events without blocking.
+#[cfg(feature = "async")]
+async fn roundtrip_async<D>(
+    conn: &Connection,
+    queue: &mut EventQueue<D>,
+    state: &mut D,
+    fd: &async_io::Async<OwnedFd>,
+) -> Result<(), Error>
+where
+    D: Dispatch<WlCallback, Arc<AtomicBool>> + 'static,
+{
+    let done = Arc::new(AtomicBool::new(false));
+    conn.display().sync(&queue.handle(), done.clone());
+    queue
+        .flush()
+        .map_err(|err| Error::WaylandCommunication(err.into()))?;
+
+    loop {
+        queue
+            .dispatch_pending(state)
+            .map_err(Error::WaylandCommunication)?;
+
+        if done.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let Some(guard) = queue.prepare_read() else {
+            continue;
+        };
+
+        // Another event queue may have read our events right before the read was prepared.
+        if queue
+            .dispatch_pending(state)
+            .map_err(Error::WaylandCommunication)?
+            > 0
+        {
+            continue;
+        }
+
+        fd.readable()
+            .await
+            .map_err(|err| Error::WaylandCommunication(WaylandError::Io(err).into()))?;
+
+        read_events(guard).map_err(|err| Error::WaylandCommunication(err.into()))?;
+    }
+}
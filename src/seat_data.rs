@@ -2,6 +2,9 @@ use crate::data_control::{Device, Offer};
 
 #[derive(Default)]
 pub struct SeatData {
+    /// The numeric name of this seat's global.
+    pub global_name: u32,
+
     /// The name of this seat, if any.
     pub name: Option<String>,
 
@@ -0,0 +1,193 @@
+//! Saving the clipboard contents to a file and restoring them.
+//!
+//! A [`Snapshot`] holds the clipboard contents in all offered MIME types, as returned by
+//! [`paste::get_all_contents()`]. It can be written to a file and read back later, even after a
+//! reboot, and then copied back to the clipboard.
+//!
+//! # File format
+//!
+//! The snapshot file is a small binary format. All integers are little-endian.
+//!
+//! | Field       | Size         | Description                                  |
+//! |-------------|--------------|----------------------------------------------|
+//! | magic       | 8 bytes      | `WLCLIPRS`                                   |
+//! | version     | 1 byte       | Format version, currently `1`                |
+//! | count       | 4 bytes      | Number of entries                            |
+//!
+//! It's followed by `count` entries, sorted by the MIME type:
+//!
+//! | Field       | Size         | Description                                  |
+//! |-------------|--------------|----------------------------------------------|
+//! | type length | 4 bytes      | Length of the MIME type in bytes             |
+//! | MIME type   | type length  | The MIME type, UTF-8                         |
+//! | data length | 8 bytes      | Length of the data in bytes                  |
+//! | data        | data length  | The clipboard contents in this MIME type     |
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use crate::copy::{self, CopyHandle, MimeSource};
+use crate::paste;
+
+const MAGIC: &[u8; 8] = b"WLCLIPRS";
+const VERSION: u8 = 1;
+
+/// Clipboard contents in all offered MIME types.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Snapshot {
+    /// The contents, keyed by the MIME type.
+    pub contents: HashMap<String, Vec<u8>>,
+}
+
+/// Errors that can occur for reading a snapshot.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Couldn't read the snapshot")]
+    Read(#[source] io::Error),
+
+    #[error("The file is not a clipboard snapshot")]
+    InvalidMagic,
+
+    #[error("Unsupported snapshot format version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Invalid MIME type in the snapshot")]
+    InvalidMimeType,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the clipboard contents.
+    ///
+    /// See [`paste::Options::get_all_contents()`].
+    #[inline]
+    pub fn take(
+        options: paste::Options,
+        clipboard: paste::ClipboardType,
+        seat: paste::Seat<'_>,
+    ) -> Result<Self, paste::Error> {
+        let contents = options.get_all_contents(clipboard, seat)?;
+        Ok(Self { contents })
+    }
+
+    /// Writes the snapshot in the snapshot file format.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&len_u32(self.contents.len())?.to_le_bytes())?;
+
+        // Sort the entries to make the output deterministic.
+        let mut entries = self.contents.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(mime_type, _)| *mime_type);
+
+        for (mime_type, data) in entries {
+            writer.write_all(&len_u32(mime_type.len())?.to_le_bytes())?;
+            writer.write_all(mime_type.as_bytes())?;
+            writer.write_all(&(data.len() as u64).to_le_bytes())?;
+            writer.write_all(data)?;
+        }
+
+        writer.flush()
+    }
+
+    /// Reads a snapshot in the snapshot file format.
+    pub fn read_from(mut reader: impl Read) -> Result<Self, Error> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic).map_err(Error::Read)?;
+        if &magic != MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+
+        let [version] = read_array(&mut reader)?;
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let count = u32::from_le_bytes(read_array(&mut reader)?);
+
+        let mut contents = HashMap::new();
+        for _ in 0..count {
+            let len = u32::from_le_bytes(read_array(&mut reader)?);
+            let mime_type = read_vec(&mut reader, len.into())?;
+            let mime_type = String::from_utf8(mime_type).map_err(|_| Error::InvalidMimeType)?;
+
+            let len = u64::from_le_bytes(read_array(&mut reader)?);
+            let data = read_vec(&mut reader, len)?;
+
+            contents.insert(mime_type, data);
+        }
+
+        Ok(Self { contents })
+    }
+
+    /// Saves the snapshot to a file.
+    ///
+    /// Snapshots can contain sensitive data such as passwords, so a new file is created readable
+    /// only by the current user. An existing file keeps its permissions.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        self.write_to(BufWriter::new(file))
+    }
+
+    /// Loads a snapshot from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::Read)?;
+        Self::read_from(BufReader::new(file))
+    }
+
+    /// Converts the snapshot into data sources for copying, one per MIME type.
+    ///
+    /// The result is meant for [`copy::copy_multi()`] or [`copy::prepare_copy_multi()`]. Since
+    /// the snapshot already has the exact offered MIME types, consider setting
+    /// [`copy::Options::omit_additional_text_mime_types()`] when copying it.
+    pub fn into_sources(self) -> Vec<MimeSource> {
+        self.contents
+            .into_iter()
+            .map(|(mime_type, data)| MimeSource {
+                source: copy::Source::Bytes(data.into()),
+                mime_type: copy::MimeType::Specific(mime_type),
+            })
+            .collect()
+    }
+
+    /// Copies the snapshot back to the clipboard.
+    ///
+    /// See [`copy::copy_multi()`].
+    #[inline]
+    pub fn restore(self, options: copy::Options) -> Result<CopyHandle, copy::Error> {
+        copy::copy_multi(options, self.into_sources())
+    }
+}
+
+fn len_u32(len: usize) -> io::Result<u32> {
+    u32::try_from(len)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too long for a snapshot"))
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], Error> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf).map_err(Error::Read)?;
+    Ok(buf)
+}
+
+fn read_vec(reader: &mut impl Read, len: u64) -> Result<Vec<u8>, Error> {
+    // Don't trust the length for the allocation, the file may be truncated or corrupted.
+    let mut buf = Vec::new();
+    reader
+        .take(len)
+        .read_to_end(&mut buf)
+        .map_err(Error::Read)?;
+
+    if buf.len() as u64 != len {
+        return Err(Error::Read(io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    Ok(buf)
+}
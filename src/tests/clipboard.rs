@@ -0,0 +1,126 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc::channel;
+
+use wayland_client::Connection;
+use wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
+
+use crate::clipboard::Clipboard;
+use crate::common::socket_path;
+use crate::tests::state::*;
+use crate::tests::TestServer;
+use crate::utils::Protocol;
+use crate::{copy, paste};
+
+#[test]
+fn clipboard_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        data_device_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut clipboard = Clipboard::new_internal(Some(socket_name)).unwrap();
+    assert_eq!(rx.recv().unwrap(), "seat0");
+
+    let result = clipboard.mime_types(paste::ClipboardType::Regular, paste::Seat::Unspecified);
+    assert!(matches!(result, Err(paste::Error::ClipboardEmpty)));
+
+    let handle = clipboard
+        .copy(
+            copy::Options::new(),
+            copy::Source::Bytes([1, 3, 3, 7][..].into()),
+            copy::MimeType::Specific("test".into()),
+        )
+        .unwrap();
+
+    let mime_types = clipboard
+        .mime_types(paste::ClipboardType::Regular, paste::Seat::Unspecified)
+        .unwrap();
+    assert_eq!(mime_types, HashSet::from(["test".into()]));
+
+    let (mut read, mime_type) = clipboard
+        .contents(
+            paste::ClipboardType::Regular,
+            paste::Seat::Specific("seat0"),
+            paste::MimeType::Any,
+        )
+        .unwrap();
+    assert_eq!(mime_type, "test");
+
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, [1, 3, 3, 7]);
+
+    clipboard
+        .clear(copy::ClipboardType::Regular, copy::Seat::All)
+        .unwrap();
+    handle.join().unwrap();
+
+    let result = clipboard.mime_types(paste::ClipboardType::Regular, paste::Seat::Unspecified);
+    assert!(matches!(result, Err(paste::Error::ClipboardEmpty)));
+
+    // The copy reused the data device of the clipboard.
+    assert!(rx.try_recv().is_err());
+}
+
+#[test]
+fn clipboard_from_connection_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from([("test".into(), vec![1, 3, 3, 7])]),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_path = socket_path(server.socket_name().to_owned()).unwrap();
+    server.run(state);
+
+    let stream = UnixStream::connect(socket_path).unwrap();
+    let conn = Connection::from_socket(stream).unwrap();
+    let mut clipboard = Clipboard::from_connection(conn, Protocol::Auto).unwrap();
+
+    let (mut read, mime_type) = clipboard
+        .contents(
+            paste::ClipboardType::Regular,
+            paste::Seat::Unspecified,
+            paste::MimeType::Any,
+        )
+        .unwrap();
+    assert_eq!(mime_type, "test");
+
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, [1, 3, 3, 7]);
+}
@@ -1,9 +1,15 @@
 use std::collections::HashMap;
-use std::io::Read;
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::slice;
 use std::sync::mpsc::channel;
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 use proptest::prelude::*;
+use rustix::event::{poll, PollFd, PollFlags};
+use rustix::fs::{fcntl_get_seals, SealFlags};
 use wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
 
 use crate::copy::*;
@@ -11,6 +17,7 @@ use crate::paste;
 use crate::paste::get_contents_internal;
 use crate::tests::state::*;
 use crate::tests::TestServer;
+use crate::utils::Protocol;
 
 #[test]
 fn clear_test() {
@@ -99,6 +106,708 @@ fn copy_test() {
     clear_internal(ClipboardType::Both, Seat::All, Some(socket_name)).unwrap();
 }
 
+#[test]
+fn copy_reader_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let sources = vec![MimeSource {
+        source: Source::Reader(Arc::new(Mutex::new(Cursor::new(vec![1, 3, 3, 7])))),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    copy_internal(Options::new(), sources, Some(socket_name.clone())).unwrap();
+
+    // Wait for the copy.
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["test"]);
+
+    let (mut read, mime_type) = get_contents_internal(
+        paste::ClipboardType::Regular,
+        paste::Seat::Unspecified,
+        paste::MimeType::Any,
+        Some(socket_name.clone()),
+    )
+    .unwrap();
+
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+
+    assert_eq!(mime_type, "test");
+    assert_eq!(contents, [1, 3, 3, 7]);
+
+    clear_internal(ClipboardType::Both, Seat::All, Some(socket_name)).unwrap();
+}
+
+fn copy_file(snapshot: bool) -> (Vec<u8>, Vec<u8>) {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("test.html");
+    fs::write(&path, b"<b>first</b>").unwrap();
+
+    let sources = vec![MimeSource {
+        source: Source::File {
+            path: path.clone(),
+            snapshot,
+        },
+        mime_type: MimeType::Autodetect,
+    }];
+    let mut opts = Options::new();
+    opts.omit_additional_text_mime_types(true);
+    copy_internal(opts, sources, Some(socket_name.clone())).unwrap();
+
+    // Wait for the copy.
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["text/html"]);
+
+    let paste = || {
+        let (mut read, mime_type) = get_contents_internal(
+            paste::ClipboardType::Regular,
+            paste::Seat::Unspecified,
+            paste::MimeType::Any,
+            Some(socket_name.clone()),
+        )
+        .unwrap();
+        assert_eq!(mime_type, "text/html");
+
+        let mut contents = vec![];
+        read.read_to_end(&mut contents).unwrap();
+        contents
+    };
+
+    let before = paste();
+    fs::write(&path, b"<b>second</b>").unwrap();
+    let after = paste();
+
+    clear_internal(ClipboardType::Both, Seat::All, Some(socket_name)).unwrap();
+
+    (before, after)
+}
+
+#[test]
+fn copy_file_test() {
+    let (before, after) = copy_file(false);
+    assert_eq!(before, b"<b>first</b>");
+    assert_eq!(after, b"<b>second</b>");
+}
+
+#[test]
+fn copy_file_snapshot_test() {
+    let (before, after) = copy_file(true);
+    assert_eq!(before, b"<b>first</b>");
+    assert_eq!(after, b"<b>first</b>");
+}
+
+#[test]
+fn copy_provider_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let requested = Arc::new(Mutex::new(Vec::new()));
+    let provider = {
+        let requested = requested.clone();
+        Arc::new(move |mime_type: &str, target: &mut dyn Write| {
+            requested.lock().unwrap().push(mime_type.to_owned());
+            target.write_all(mime_type.as_bytes())
+        })
+    };
+
+    let sources = vec![
+        MimeSource {
+            source: Source::Provider(provider.clone()),
+            mime_type: MimeType::Specific("test".into()),
+        },
+        MimeSource {
+            source: Source::Provider(provider),
+            mime_type: MimeType::Specific("test2".into()),
+        },
+    ];
+    copy_internal(Options::new(), sources, Some(socket_name.clone())).unwrap();
+
+    // Wait for the copy.
+    let mut mime_types = rx.recv().unwrap().unwrap();
+    mime_types.sort_unstable();
+    assert_eq!(mime_types, ["test", "test2"]);
+
+    // Nothing is generated until pasted.
+    assert!(requested.lock().unwrap().is_empty());
+
+    let (mut read, _) = get_contents_internal(
+        paste::ClipboardType::Regular,
+        paste::Seat::Unspecified,
+        paste::MimeType::Specific("test2"),
+        Some(socket_name.clone()),
+    )
+    .unwrap();
+
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+
+    assert_eq!(contents, b"test2");
+    assert_eq!(*requested.lock().unwrap(), ["test2"]);
+
+    clear_internal(ClipboardType::Both, Seat::All, Some(socket_name)).unwrap();
+}
+
+#[test]
+fn copy_poll_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let mut opts = Options::new();
+    opts.serve_requests(ServeRequests::Only(1));
+    let mut prepared_copy =
+        prepare_copy_internal(opts, sources, Some(socket_name.clone())).unwrap();
+
+    // The first poll sends the offer.
+    assert!(!prepared_copy.poll().unwrap());
+
+    // Wait for the copy.
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["test"]);
+
+    let paste = thread::spawn(move || {
+        let (mut read, _) = get_contents_internal(
+            paste::ClipboardType::Regular,
+            paste::Seat::Unspecified,
+            paste::MimeType::Any,
+            Some(socket_name),
+        )
+        .unwrap();
+
+        let mut contents = vec![];
+        read.read_to_end(&mut contents).unwrap();
+        contents
+    });
+
+    loop {
+        let mut fds = [PollFd::new(&prepared_copy, PollFlags::IN)];
+        poll(&mut fds, -1).unwrap();
+
+        if prepared_copy.poll().unwrap() {
+            break;
+        }
+    }
+    prepared_copy.finish().unwrap();
+
+    assert_eq!(paste.join().unwrap(), [1, 3, 3, 7]);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn copy_async_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let mut opts = Options::new();
+    opts.serve_requests(ServeRequests::Only(1));
+    let prepared_copy = prepare_copy_internal(opts, sources, Some(socket_name.clone())).unwrap();
+
+    let paste = thread::spawn(move || {
+        // Wait for the copy.
+        let mime_types = rx.recv().unwrap().unwrap();
+        assert_eq!(mime_types, ["test"]);
+
+        let (mut read, _) = get_contents_internal(
+            paste::ClipboardType::Regular,
+            paste::Seat::Unspecified,
+            paste::MimeType::Any,
+            Some(socket_name),
+        )
+        .unwrap();
+
+        let mut contents = vec![];
+        read.read_to_end(&mut contents).unwrap();
+        contents
+    });
+
+    async_io::block_on(prepared_copy.serve_async()).unwrap();
+
+    assert_eq!(paste.join().unwrap(), [1, 3, 3, 7]);
+}
+
+#[test]
+fn copy_cancel_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let handle = copy_internal(Options::new(), sources, Some(socket_name.clone())).unwrap();
+
+    // Wait for the copy.
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["test"]);
+    assert!(handle.is_serving());
+
+    handle.cancel();
+    handle.join().unwrap();
+}
+
+#[test]
+fn copy_events_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let handle = copy_internal(Options::new(), sources, Some(socket_name.clone())).unwrap();
+
+    // Wait for the copy.
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["test"]);
+
+    let (mut read, _) = get_contents_internal(
+        paste::ClipboardType::Regular,
+        paste::Seat::Unspecified,
+        paste::MimeType::Specific("test"),
+        Some(socket_name.clone()),
+    )
+    .unwrap();
+
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, [1, 3, 3, 7]);
+
+    // Take over the clipboard to cancel the copy.
+    clear_internal(ClipboardType::Regular, Seat::All, Some(socket_name)).unwrap();
+
+    // The events end once serving finishes.
+    let events = handle.events().unwrap().iter().collect::<Vec<_>>();
+    handle.join().unwrap();
+
+    let [served, cancelled] = &events[..] else {
+        panic!("unexpected events: {events:?}");
+    };
+
+    let CopyEvent::Served { request, bytes, .. } = served else {
+        panic!("expected a Served event, got {served:?}");
+    };
+    assert_eq!(
+        *request,
+        PasteRequest {
+            mime_type: "test".into(),
+            seat: Some("seat0".into()),
+            clipboard: ClipboardType::Regular,
+        }
+    );
+    assert_eq!(*bytes, 4);
+
+    let CopyEvent::Cancelled { seat, clipboard } = cancelled else {
+        panic!("expected a Cancelled event, got {cancelled:?}");
+    };
+    assert_eq!(*seat, Some("seat0".into()));
+    assert_eq!(*clipboard, ClipboardType::Regular);
+}
+
+#[test]
+fn copy_failed_event_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let provider = |_: &str, _: &mut dyn Write| Err(io::Error::other("test error"));
+    let sources = vec![MimeSource {
+        source: Source::Provider(Arc::new(provider)),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let handle = copy_internal(Options::new(), sources, Some(socket_name.clone())).unwrap();
+
+    // Wait for the copy.
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["test"]);
+
+    let (mut read, _) = get_contents_internal(
+        paste::ClipboardType::Regular,
+        paste::Seat::Unspecified,
+        paste::MimeType::Specific("test"),
+        Some(socket_name),
+    )
+    .unwrap();
+
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+    assert!(contents.is_empty());
+
+    // Serving stops after the failure.
+    let events = handle.events().unwrap().iter().collect::<Vec<_>>();
+    let result = handle.join();
+
+    let [CopyEvent::Failed { request, error, .. }] = &events[..] else {
+        panic!("unexpected events: {events:?}");
+    };
+    assert_eq!(request.mime_type, "test");
+    let DataSourceError::Provider(err) = &**error else {
+        panic!("expected a provider error, got {error:?}");
+    };
+    assert_eq!(err.to_string(), "test error");
+
+    assert!(matches!(
+        result,
+        Err(Error::Paste(DataSourceError::Provider(_)))
+    ));
+}
+
+#[test]
+fn copy_expiry_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let mut opts = Options::new();
+    opts.expire_after(Duration::from_millis(100))
+        .clear_on_expiry(true);
+    let handle = copy_internal(opts, sources, Some(socket_name)).unwrap();
+
+    // Wait for the copy.
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["test"]);
+
+    // The clipboard is cleared on expiry.
+    let mime_types = rx.recv().unwrap();
+    assert_eq!(mime_types, None);
+
+    let events = handle.events().unwrap().iter().collect::<Vec<_>>();
+    handle.join().unwrap();
+    assert!(
+        matches!(events[..], [CopyEvent::Expired]),
+        "unexpected events: {events:?}"
+    );
+}
+
+#[test]
+fn copy_restore_previous_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let paste = |mime_type| {
+        let (mut read, _) = get_contents_internal(
+            paste::ClipboardType::Regular,
+            paste::Seat::Unspecified,
+            paste::MimeType::Specific(mime_type),
+            Some(socket_name.clone()),
+        )
+        .unwrap();
+
+        let mut contents = vec![];
+        read.read_to_end(&mut contents).unwrap();
+        contents
+    };
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("previous".into()),
+    }];
+    let previous_handle =
+        copy_internal(Options::new(), sources, Some(socket_name.clone())).unwrap();
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["previous"]);
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([4, 2][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let mut opts = Options::new();
+    opts.serve_requests(ServeRequests::Only(1))
+        .restore_previous(true);
+    let handle = copy_internal(opts, sources, Some(socket_name.clone())).unwrap();
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["test"]);
+
+    // The previous copy is taken over.
+    previous_handle.join().unwrap();
+
+    assert_eq!(paste("test"), [4, 2]);
+
+    // The previous contents are restored after the last request.
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["previous"]);
+    assert_eq!(paste("previous"), [1, 3, 3, 7]);
+    assert!(handle.is_serving());
+
+    clear_internal(ClipboardType::Regular, Seat::All, Some(socket_name)).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn copy_restore_previous_limits_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    // The previous contents are slow to arrive.
+    let provider = |_: &str, target: &mut dyn Write| {
+        thread::sleep(Duration::from_millis(300));
+        target.write_all(&[1, 3, 3, 7])
+    };
+    let sources = vec![MimeSource {
+        source: Source::Provider(Arc::new(provider)),
+        mime_type: MimeType::Specific("previous".into()),
+    }];
+    let previous_handle =
+        copy_internal(Options::new(), sources, Some(socket_name.clone())).unwrap();
+    let mime_types = rx.recv().unwrap().unwrap();
+    assert_eq!(mime_types, ["previous"]);
+
+    let copy = |opts: &mut Options| {
+        let sources = vec![MimeSource {
+            source: Source::Bytes([4, 2][..].into()),
+            mime_type: MimeType::Specific("test".into()),
+        }];
+        opts.restore_previous(true);
+        copy_internal(opts.clone(), sources, Some(socket_name.clone()))
+    };
+
+    let result = copy(Options::new().snapshot_max_size(2));
+    assert!(matches!(
+        result,
+        Err(Error::Snapshot(paste::Error::TooLarge { max_size: 2 }))
+    ));
+
+    let result = copy(Options::new().snapshot_timeout(Duration::from_millis(50)));
+    assert!(matches!(
+        result,
+        Err(Error::Snapshot(paste::Error::Timeout))
+    ));
+
+    // The previous copy fails to write into the abandoned pipe.
+    assert!(previous_handle.join().is_err());
+}
+
 #[test]
 fn copy_multi_test() {
     let server = TestServer::new();
@@ -335,6 +1044,29 @@ fn copy_large() {
     clear_internal(ClipboardType::Both, Seat::All, Some(socket_name)).unwrap();
 }
 
+#[test]
+fn memory_storage_sealed_test() {
+    let source = Source::Bytes([1, 3, 3, 7][..].into());
+    let mime_type = MimeType::Specific("test".into());
+    let (_, data) = make_source(source, mime_type, false, Storage::Memory).unwrap();
+    let Data::Memory(file) = data else {
+        panic!("expected the data in a memfd");
+    };
+
+    // Nothing can change the data once it's offered.
+    let seals = fcntl_get_seals(&*file).unwrap();
+    assert_eq!(
+        seals,
+        SealFlags::SHRINK | SealFlags::GROW | SealFlags::WRITE | SealFlags::SEAL
+    );
+    assert!((&*file).write_all(b"x").is_err());
+
+    let mut contents = vec![];
+    (&*file).seek(SeekFrom::Start(0)).unwrap();
+    (&*file).read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, [1, 3, 3, 7]);
+}
+
 proptest! {
     #[test]
     fn copy_randomized(
@@ -342,6 +1074,7 @@ proptest! {
         clipboard_type: ClipboardType,
         source: Source,
         mime_type: MimeType,
+        storage: Storage,
         seat_index: prop::sample::Index,
         clipboard_type_index: prop::sample::Index,
     ) {
@@ -374,7 +1107,9 @@ proptest! {
 
         let expected_contents = match &source {
             Source::Bytes(bytes) => bytes.clone(),
-            Source::StdIn => unreachable!(),
+            Source::StdIn | Source::Reader(_) | Source::File { .. } | Source::Provider(_) => {
+                unreachable!()
+            }
         };
 
         let sources = vec![MimeSource {
@@ -385,6 +1120,7 @@ proptest! {
         opts.clipboard(clipboard_type);
         opts.seat(Seat::Specific(seat_name.clone()));
         opts.omit_additional_text_mime_types(true);
+        opts.storage(storage);
         copy_internal(opts, sources, Some(socket_name.clone())).unwrap();
 
         // Wait for the copy.
@@ -393,7 +1129,7 @@ proptest! {
         match &mime_type {
             MimeType::Autodetect => unreachable!(),
             MimeType::Text => assert_eq!(mime_types, ["text/plain"]),
-            MimeType::Specific(mime) => assert_eq!(mime_types, [mime.clone()]),
+            MimeType::Specific(mime) => assert_eq!(mime_types, slice::from_ref(mime)),
         }
 
         let paste_mime_type = match mime_type {
@@ -418,3 +1154,189 @@ proptest! {
         clear_internal(clipboard_type, Seat::Specific(seat_name), Some(socket_name)).unwrap();
     }
 }
+
+#[test]
+fn copy_ext_only_unsupported() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut opts = Options::new();
+    opts.protocol(Protocol::ExtOnly);
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let result = prepare_copy_internal(opts, sources, Some(socket_name));
+    assert!(matches!(result, Err(Error::MissingProtocol { .. })));
+}
+
+#[test]
+fn copy_to_new_seat_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        seat_to_add: Some("seat1".into()),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let handle = copy_internal(Options::new(), sources, Some(socket_name.clone())).unwrap();
+
+    // The copy to seat0 adds seat1, which gets the copy too.
+    assert_eq!(rx.recv().unwrap().unwrap(), ["test"]);
+    assert_eq!(rx.recv().unwrap().unwrap(), ["test"]);
+
+    let (mut read, mime_type) = get_contents_internal(
+        paste::ClipboardType::Regular,
+        paste::Seat::Specific("seat1"),
+        paste::MimeType::Any,
+        Some(socket_name),
+    )
+    .unwrap();
+
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+    assert_eq!(mime_type, "test");
+    assert_eq!(contents, [1, 3, 3, 7]);
+
+    handle.cancel();
+    handle.join().unwrap();
+}
+
+#[test]
+fn copy_skips_other_new_seat_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+    let (device_tx, device_rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        data_device_sender: Some(device_tx),
+        seat_to_add: Some("seat1".into()),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let mut options = Options::new();
+    options.seat(Seat::Specific("seat0".into()));
+    let handle = copy_internal(options, sources, Some(socket_name.clone())).unwrap();
+
+    // The copy to seat0 adds seat1, which doesn't get the copy.
+    assert_eq!(rx.recv().unwrap().unwrap(), ["test"]);
+
+    let (mut read, _) = get_contents_internal(
+        paste::ClipboardType::Regular,
+        paste::Seat::Specific("seat0"),
+        paste::MimeType::Any,
+        Some(socket_name),
+    )
+    .unwrap();
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, [1, 3, 3, 7]);
+
+    handle.cancel();
+    handle.join().unwrap();
+
+    // The copy created a data device only for seat0, the paste created one for every seat.
+    let mut devices = device_rx.try_iter().collect::<Vec<_>>();
+    devices.sort();
+    assert_eq!(devices, ["seat0", "seat0", "seat1"]);
+}
+
+#[test]
+fn copy_removed_seat_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let (tx, rx) = channel();
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        selection_updated_sender: Some(tx),
+        seat_to_add: Some("seat1".into()),
+        remove_added_seat: true,
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let sources = vec![MimeSource {
+        source: Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: MimeType::Specific("test".into()),
+    }];
+    let handle = copy_internal(Options::new(), sources, Some(socket_name.clone())).unwrap();
+
+    // The copy to seat0 adds seat1, and the copy to seat1 removes it.
+    assert_eq!(rx.recv().unwrap().unwrap(), ["test"]);
+    assert_eq!(rx.recv().unwrap().unwrap(), ["test"]);
+
+    // With the source of the removed seat gone, taking over seat0 finishes the copy.
+    clear_internal(ClipboardType::Regular, Seat::All, Some(socket_name)).unwrap();
+    handle.join().unwrap();
+}
@@ -1,12 +1,19 @@
 use std::collections::{HashMap, HashSet};
 use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use proptest::prelude::*;
 use wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
 
+use crate::clipboard::Clipboard;
+use crate::common::socket_path;
+use crate::copy;
 use crate::paste::*;
+use crate::tests::state::State;
 use crate::tests::state::*;
 use crate::tests::TestServer;
+use crate::utils::Protocol;
 
 #[test]
 fn get_mime_types_test() {
@@ -45,6 +52,142 @@ fn get_mime_types_test() {
     assert_eq!(mime_types, expected);
 }
 
+#[test]
+fn get_mime_types_ordered_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    // The test compositor offers the MIME types sorted.
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from(
+                        ["a", "b", "c", "d", "e", "f", "g", "h"].map(|x| (x.into(), x.into())),
+                    ),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mime_types = get_mime_types_ordered_internal(
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        Some(socket_name),
+    )
+    .unwrap();
+    assert_eq!(mime_types, ["a", "b", "c", "d", "e", "f", "g", "h"]);
+}
+
+#[test]
+fn get_contents_any_picks_first_offered() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    // The test compositor offers the MIME types sorted.
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from(
+                        ["a", "b", "c", "d", "e", "f", "g", "h"].map(|x| (x.into(), x.into())),
+                    ),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    // Without a text MIME type, the first offered one is picked.
+    let (mut read, mime_type) = get_contents_internal(
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        MimeType::Any,
+        Some(socket_name),
+    )
+    .unwrap();
+    assert_eq!(mime_type, "a");
+
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, b"a");
+}
+
+#[test]
+fn get_contents_preferences() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    // The test compositor offers the MIME types sorted.
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from(
+                        [
+                            "image/jpeg",
+                            "image/png",
+                            "text/html",
+                            "text/plain; charset=UTF-8",
+                        ]
+                        .map(|x| (x.into(), x.into())),
+                    ),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut clipboard = Clipboard::new_internal(Some(socket_name)).unwrap();
+    let mut get = |preferences: &[&str]| {
+        clipboard
+            .contents(
+                ClipboardType::Regular,
+                Seat::Unspecified,
+                MimeType::Preferences(preferences),
+            )
+            .map(|(_, mime_type)| mime_type)
+    };
+
+    assert_eq!(get(&["image/png"]).unwrap(), "image/png");
+    assert_eq!(get(&["image/webp", "image/*"]).unwrap(), "image/jpeg");
+    assert_eq!(
+        get(&["text/*;charset=utf-8", "text/*"]).unwrap(),
+        "text/plain; charset=UTF-8"
+    );
+    assert_eq!(get(&["application/json", "*/*"]).unwrap(), "image/jpeg");
+    assert!(matches!(get(&["application/*"]), Err(Error::NoMimeType)));
+    assert!(matches!(get(&[]), Err(Error::NoMimeType)));
+}
+
 #[test]
 fn get_mime_types_no_data_control() {
     let server = TestServer::new();
@@ -65,7 +208,10 @@ fn get_mime_types_no_data_control() {
 
     let result =
         get_mime_types_internal(ClipboardType::Regular, Seat::Unspecified, Some(socket_name));
-    assert!(matches!(result, Err(Error::MissingProtocol { version: 1 })));
+    assert!(matches!(
+        result,
+        Err(Error::MissingProtocol { version: 1, .. })
+    ));
 }
 
 #[test]
@@ -88,7 +234,10 @@ fn get_mime_types_no_data_control_2() {
 
     let result =
         get_mime_types_internal(ClipboardType::Primary, Seat::Unspecified, Some(socket_name));
-    assert!(matches!(result, Err(Error::MissingProtocol { version: 2 })));
+    assert!(matches!(
+        result,
+        Err(Error::MissingProtocol { version: 2, .. })
+    ));
 }
 
 #[test]
@@ -298,6 +447,286 @@ fn get_contents_wrong_mime_type() {
     assert!(matches!(result, Err(Error::NoMimeType)));
 }
 
+#[test]
+fn read_contents_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from([("application/octet-stream".into(), vec![1, 3, 3, 7])]),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut options = Options::new();
+    options.timeout(Duration::from_secs(5)).max_size(4);
+    let (contents, mime_type) = read_contents_internal(
+        options,
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        MimeType::Any,
+        Some(socket_name),
+    )
+    .unwrap();
+
+    assert_eq!(mime_type, "application/octet-stream");
+    assert_eq!(contents, [1, 3, 3, 7]);
+}
+
+#[test]
+fn read_contents_too_large() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from([("application/octet-stream".into(), vec![1, 3, 3, 7])]),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut options = Options::new();
+    options.max_size(3);
+    let result = read_contents_internal(
+        options,
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        MimeType::Any,
+        Some(socket_name),
+    );
+    assert!(matches!(result, Err(Error::TooLarge { max_size: 3 })));
+}
+
+#[test]
+fn read_contents_timeout() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from([("application/octet-stream".into(), vec![1, 3, 3, 7])]),
+                }),
+                ..Default::default()
+            },
+        )]),
+        stall_transfers: true,
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    // Keep the state, and with it the stalled transfers, alive after the client disconnects.
+    let state = Arc::new(Mutex::new(state));
+
+    let socket_name = server.socket_name().to_owned();
+    server.run_mutex(state.clone());
+
+    let mut options = Options::new();
+    options.timeout(Duration::from_millis(100));
+    let result = read_contents_internal(
+        options,
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        MimeType::Any,
+        Some(socket_name),
+    );
+    assert!(matches!(result, Err(Error::Timeout)));
+}
+
+#[test]
+fn get_all_contents_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let data = HashMap::from([
+        ("text/plain".into(), vec![1, 3, 3, 7]),
+        ("text/html".into(), vec![1, 2, 3]),
+        ("image/png".into(), vec![4, 5, 6, 7, 8]),
+    ]);
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered { data: data.clone() }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let contents = get_all_contents_internal(
+        Options::new(),
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        Some(socket_name),
+    )
+    .unwrap();
+    assert_eq!(contents, data);
+}
+
+#[test]
+fn get_all_contents_filtered() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from([
+                        ("text/plain".into(), vec![1, 3, 3, 7]),
+                        ("text/html".into(), vec![1, 2, 3]),
+                        ("image/png".into(), vec![4, 5, 6, 7, 8]),
+                    ]),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut options = Options::new();
+    options
+        .allow_mime_types(HashSet::from(["text/plain".into(), "text/html".into()]))
+        .deny_mime_types(HashSet::from(["text/html".into()]));
+    let contents = get_all_contents_internal(
+        options,
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        Some(socket_name),
+    )
+    .unwrap();
+    assert_eq!(
+        contents,
+        HashMap::from([("text/plain".into(), vec![1, 3, 3, 7])])
+    );
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn get_contents_async_test() {
+    use futures_lite::AsyncReadExt;
+
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from([("application/octet-stream".into(), vec![1, 3, 3, 7])]),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    async_io::block_on(async {
+        let (mut read, mime_type) = get_contents_async_internal(
+            ClipboardType::Regular,
+            Seat::Unspecified,
+            MimeType::Any,
+            Some(socket_name),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(mime_type, "application/octet-stream");
+
+        let mut contents = vec![];
+        read.read_to_end(&mut contents).await.unwrap();
+        assert_eq!(contents, [1, 3, 3, 7]);
+    });
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn get_contents_async_unresponsive_test() {
+    use std::os::unix::net::UnixListener;
+
+    // A compositor that never answers.
+    let dir = tempfile::tempdir().unwrap();
+    let socket_path = dir.path().join("wayland-unresponsive");
+    let _listener = UnixListener::bind(&socket_path).unwrap();
+
+    let timed_out = async_io::block_on(futures_lite::future::or(
+        async {
+            let _ = get_contents_async_internal(
+                ClipboardType::Regular,
+                Seat::Unspecified,
+                MimeType::Any,
+                Some(socket_path.into()),
+            )
+            .await;
+            false
+        },
+        async {
+            async_io::Timer::after(Duration::from_millis(100)).await;
+            true
+        },
+    ));
+
+    // Waiting for the compositor left the executor free to run the timer.
+    assert!(timed_out);
+}
+
 proptest! {
     #[test]
     fn get_mime_types_randomized(
@@ -414,3 +843,236 @@ proptest! {
         }
     }
 }
+
+#[test]
+fn watch_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut watcher = watch_internal(
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        Some(socket_name.clone()),
+    )
+    .unwrap();
+
+    // The clipboard is empty at first.
+    assert!(watcher.next().unwrap().unwrap().is_none());
+
+    let sources = vec![copy::MimeSource {
+        source: copy::Source::Bytes([1, 3, 3, 7][..].into()),
+        mime_type: copy::MimeType::Specific("test".into()),
+    }];
+    copy::copy_internal(copy::Options::new(), sources, Some(socket_name.clone())).unwrap();
+
+    let offer = watcher.next().unwrap().unwrap().unwrap();
+    assert_eq!(offer.mime_types(), ["test"]);
+
+    let (mut read, mime_type) = offer.receive(MimeType::Any).unwrap();
+    assert_eq!(mime_type, "test");
+
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, [1, 3, 3, 7]);
+
+    copy::clear_internal(
+        copy::ClipboardType::Regular,
+        copy::Seat::All,
+        Some(socket_name),
+    )
+    .unwrap();
+
+    assert!(watcher.next().unwrap().unwrap().is_none());
+}
+
+#[test]
+fn watch_receive_all_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut watcher = watch_internal(
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        Some(socket_name.clone()),
+    )
+    .unwrap();
+    assert!(watcher.next().unwrap().unwrap().is_none());
+
+    let sources = vec![
+        copy::MimeSource {
+            source: copy::Source::Bytes([1, 3, 3, 7][..].into()),
+            mime_type: copy::MimeType::Specific("test".into()),
+        },
+        copy::MimeSource {
+            source: copy::Source::Bytes([1, 2, 3][..].into()),
+            mime_type: copy::MimeType::Specific("test2".into()),
+        },
+        copy::MimeSource {
+            source: copy::Source::Bytes([4, 5, 6][..].into()),
+            mime_type: copy::MimeType::Specific("denied".into()),
+        },
+    ];
+    let handle =
+        copy::copy_internal(copy::Options::new(), sources, Some(socket_name.clone())).unwrap();
+
+    let offer = watcher.next().unwrap().unwrap().unwrap();
+
+    let mut options = Options::new();
+    options.deny_mime_types(HashSet::from(["denied".into()]));
+    let contents = offer.receive_all(options).unwrap();
+    assert_eq!(
+        contents,
+        HashMap::from([
+            ("test".into(), vec![1, 3, 3, 7]),
+            ("test2".into(), vec![1, 2, 3]),
+        ])
+    );
+
+    handle.cancel();
+}
+
+#[test]
+fn read_contents_wlr_only() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from([("test".into(), vec![1, 3, 3, 7])]),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut options = Options::new();
+    options.protocol(Protocol::WlrOnly);
+    let (contents, mime_type) = read_contents_internal(
+        options,
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        MimeType::Any,
+        Some(socket_name),
+    )
+    .unwrap();
+
+    assert_eq!(mime_type, "test");
+    assert_eq!(contents, [1, 3, 3, 7]);
+}
+
+#[test]
+fn read_contents_ext_only_unsupported() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from([("test".into(), vec![1, 3, 3, 7])]),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let mut options = Options::new();
+    options.protocol(Protocol::ExtOnly);
+    let result = read_contents_internal(
+        options,
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        MimeType::Any,
+        Some(socket_name),
+    );
+    assert!(matches!(result, Err(Error::MissingProtocol { .. })));
+}
+
+#[test]
+fn read_contents_display_path() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                offer: Some(OfferInfo::Buffered {
+                    data: HashMap::from([("test".into(), vec![1, 3, 3, 7])]),
+                }),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_path = socket_path(server.socket_name().to_owned()).unwrap();
+    server.run(state);
+
+    let mut options = Options::new();
+    options.display(socket_path);
+    let (contents, mime_type) = options
+        .read_contents(ClipboardType::Regular, Seat::Unspecified, MimeType::Any)
+        .unwrap();
+
+    assert_eq!(mime_type, "test");
+    assert_eq!(contents, [1, 3, 3, 7]);
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use proptest::prelude::*;
+
+use crate::snapshot::*;
+
+#[test]
+fn snapshot_format() {
+    let snapshot = Snapshot {
+        contents: HashMap::from([("text/plain".into(), b"hi".to_vec()), ("a".into(), vec![])]),
+    };
+
+    let mut buf = vec![];
+    snapshot.write_to(&mut buf).unwrap();
+
+    #[rustfmt::skip]
+    let expected = [
+        b'W', b'L', b'C', b'L', b'I', b'P', b'R', b'S',
+        1,
+        2, 0, 0, 0,
+        1, 0, 0, 0, b'a',
+        0, 0, 0, 0, 0, 0, 0, 0,
+        10, 0, 0, 0, b't', b'e', b'x', b't', b'/', b'p', b'l', b'a', b'i', b'n',
+        2, 0, 0, 0, 0, 0, 0, 0, b'h', b'i',
+    ];
+    assert_eq!(buf, expected);
+
+    assert_eq!(Snapshot::read_from(&buf[..]).unwrap(), snapshot);
+}
+
+#[test]
+fn snapshot_invalid() {
+    let result = Snapshot::read_from(&b"not a snapshot"[..]);
+    assert!(matches!(result, Err(Error::InvalidMagic)));
+
+    let result = Snapshot::read_from(&b"WLCLIPRS\x02"[..]);
+    assert!(matches!(result, Err(Error::UnsupportedVersion(2))));
+
+    // The data is shorter than its length.
+    let result =
+        Snapshot::read_from(&b"WLCLIPRS\x01\x01\0\0\0\x01\0\0\0a\x05\0\0\0\0\0\0\0abc"[..]);
+    assert!(matches!(result, Err(Error::Read(_))));
+}
+
+#[test]
+fn snapshot_save_private() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snapshot");
+
+    let snapshot = Snapshot {
+        contents: HashMap::from([("text/plain".into(), b"hunter2".to_vec())]),
+    };
+    snapshot.save(&path).unwrap();
+
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    assert_eq!(Snapshot::load(&path).unwrap(), snapshot);
+}
+
+proptest! {
+    #[test]
+    fn snapshot_roundtrip(contents: HashMap<String, Vec<u8>>) {
+        let snapshot = Snapshot { contents };
+
+        let mut buf = vec![];
+        snapshot.write_to(&mut buf).unwrap();
+        prop_assert_eq!(Snapshot::read_from(&buf[..]).unwrap(), snapshot);
+    }
+}
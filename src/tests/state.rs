@@ -7,10 +7,11 @@
 
 use std::collections::HashMap;
 use std::io::Write;
-use std::os::fd::AsFd;
+use std::os::fd::{AsFd, OwnedFd};
 use std::sync::atomic::AtomicU8;
 use std::sync::atomic::Ordering::SeqCst;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
 
 use os_pipe::PipeWriter;
 use proptest::prelude::*;
@@ -28,6 +29,7 @@ use wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_offer_v1:
 use wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_source_v1::{
     self, ZwlrDataControlSourceV1,
 };
+use wayland_server::backend::GlobalId;
 use wayland_server::protocol::wl_seat::WlSeat;
 use wayland_server::{Dispatch, GlobalDispatch, Resource};
 
@@ -57,7 +59,12 @@ impl Default for OfferInfo {
 impl OfferInfo {
     fn mime_types(&self, state: &State) -> Vec<String> {
         match self {
-            OfferInfo::Buffered { data } => data.keys().cloned().collect(),
+            OfferInfo::Buffered { data } => {
+                // Offer in a fixed order for the tests that check it.
+                let mut mime_types = data.keys().cloned().collect::<Vec<_>>();
+                mime_types.sort_unstable();
+                mime_types
+            }
             OfferInfo::Runtime { source } => state.sources[source].clone(),
         }
     }
@@ -82,9 +89,27 @@ pub struct State {
     pub seats: HashMap<String, SeatInfo>,
     #[proptest(value = "HashMap::new()")]
     pub sources: HashMap<ZwlrDataControlSourceV1, Vec<String>>,
+    #[proptest(value = "Vec::new()")]
+    pub devices: Vec<ZwlrDataControlDeviceV1>,
     #[proptest(value = "None")]
     pub selection_updated_sender: Option<Sender<Option<Vec<String>>>>,
+    // Receives the seat name of every data device created.
+    #[proptest(value = "None")]
+    pub data_device_sender: Option<Sender<String>>,
     pub set_nonblock_on_write_fd: bool,
+    // Keep the write ends of the buffered transfers open, like an unresponsive client would.
+    #[proptest(value = "false")]
+    pub stall_transfers: bool,
+    #[proptest(value = "Vec::new()")]
+    pub stalled_fds: Vec<Arc<OwnedFd>>,
+    // Add this seat once a selection is set, like a hot-plugged seat.
+    #[proptest(value = "None")]
+    pub seat_to_add: Option<String>,
+    // Remove the added seat once a selection is set on it.
+    #[proptest(value = "false")]
+    pub remove_added_seat: bool,
+    #[proptest(value = "None")]
+    pub added_seat: Option<(String, GlobalId)>,
 }
 
 server_ignore_global_impl!(State => [ZwlrDataControlManagerV1]);
@@ -98,6 +123,79 @@ impl State {
                 .create_global::<Self, WlSeat, _>(6, name.clone());
         }
     }
+
+    fn send_selection(
+        &self,
+        dhandle: &wayland_server::DisplayHandle,
+        data_device: &ZwlrDataControlDeviceV1,
+        is_primary: bool,
+    ) {
+        let name: &String = data_device.data().unwrap();
+        let info = &self.seats[name];
+        let offer_info = if is_primary {
+            &info.primary_offer
+        } else {
+            &info.offer
+        };
+
+        let offer = offer_info.as_ref().map(|offer_info| {
+            let offer = data_device
+                .client()
+                .unwrap()
+                .create_resource::<ZwlrDataControlOfferV1, _, Self>(
+                    dhandle,
+                    data_device.version(),
+                    (name.clone(), is_primary),
+                )
+                .unwrap();
+            data_device.data_offer(&offer);
+
+            for mime_type in offer_info.mime_types(self) {
+                offer.offer(mime_type);
+            }
+
+            offer
+        });
+
+        if is_primary {
+            data_device.primary_selection(offer.as_ref());
+        } else {
+            data_device.selection(offer.as_ref());
+        }
+    }
+
+    // Adds or removes the hot-plugged seat in response to a selection being set on the seat.
+    fn hotplug(&mut self, dhandle: &wayland_server::DisplayHandle, name: &str) {
+        if let Some(seat) = self.seat_to_add.take() {
+            self.seats.insert(seat.clone(), SeatInfo::default());
+            let id = dhandle.create_global::<Self, WlSeat, _>(6, seat.clone());
+            self.added_seat = Some((seat, id));
+        } else if self.remove_added_seat
+            && self
+                .added_seat
+                .as_ref()
+                .is_some_and(|(seat, _)| seat == name)
+        {
+            let (_, id) = self.added_seat.take().unwrap();
+            dhandle.remove_global::<Self>(id);
+        }
+    }
+
+    // Sends the updated selection to all data devices of the seat.
+    fn broadcast_selection(
+        &mut self,
+        dhandle: &wayland_server::DisplayHandle,
+        name: &str,
+        is_primary: bool,
+    ) {
+        self.devices.retain(|data_device| data_device.is_alive());
+
+        for data_device in &self.devices {
+            if data_device.data::<String>().unwrap() == name {
+                self.send_selection(dhandle, data_device, is_primary);
+            }
+        }
+    }
 }
 
 impl GlobalDispatch<WlSeat, String> for State {
@@ -130,8 +228,8 @@ impl Dispatch<WlSeat, String> for State {
 impl Dispatch<ZwlrDataControlManagerV1, ()> for State {
     fn request(
         state: &mut Self,
-        client: &wayland_server::Client,
-        manager: &ZwlrDataControlManagerV1,
+        _client: &wayland_server::Client,
+        _manager: &ZwlrDataControlManagerV1,
         request: <ZwlrDataControlManagerV1 as wayland_server::Resource>::Request,
         _data: &(),
         dhandle: &wayland_server::DisplayHandle,
@@ -140,38 +238,17 @@ impl Dispatch<ZwlrDataControlManagerV1, ()> for State {
         match request {
             zwlr_data_control_manager_v1::Request::GetDataDevice { id, seat } => {
                 let name: &String = seat.data().unwrap();
-                let info = &state.seats[name];
 
                 let data_device = data_init.init(id, (*name).clone());
 
-                let create_offer = |offer_info: &OfferInfo, is_primary: bool| {
-                    let offer = client
-                        .create_resource::<_, _, Self>(
-                            dhandle,
-                            manager.version(),
-                            (name.clone(), is_primary),
-                        )
-                        .unwrap();
-                    data_device.data_offer(&offer);
-
-                    for mime_type in offer_info.mime_types(state) {
-                        offer.offer(mime_type);
-                    }
+                state.send_selection(dhandle, &data_device, false);
+                state.send_selection(dhandle, &data_device, true);
 
-                    offer
-                };
+                state.devices.push(data_device);
 
-                let selection = info
-                    .offer
-                    .as_ref()
-                    .map(|offer_info| create_offer(offer_info, false));
-                data_device.selection(selection.as_ref());
-
-                let primary_selection = info
-                    .primary_offer
-                    .as_ref()
-                    .map(|offer_info| create_offer(offer_info, true));
-                data_device.primary_selection(primary_selection.as_ref());
+                if let Some(sender) = &state.data_device_sender {
+                    let _ = sender.send((*name).clone());
+                }
             }
             zwlr_data_control_manager_v1::Request::CreateDataSource { id } => {
                 let source = data_init.init(id, AtomicU8::new(0));
@@ -189,7 +266,7 @@ impl Dispatch<ZwlrDataControlDeviceV1, String> for State {
         _resource: &ZwlrDataControlDeviceV1,
         request: <ZwlrDataControlDeviceV1 as Resource>::Request,
         name: &String,
-        _dhandle: &wayland_server::DisplayHandle,
+        dhandle: &wayland_server::DisplayHandle,
         _data_init: &mut wayland_server::DataInit<'_, Self>,
     ) {
         match request {
@@ -208,6 +285,9 @@ impl Dispatch<ZwlrDataControlDeviceV1, String> for State {
                 }
                 info.offer = source.map(|source| OfferInfo::Runtime { source });
 
+                state.broadcast_selection(dhandle, name, false);
+                state.hotplug(dhandle, name);
+
                 if let Some(sender) = &state.selection_updated_sender {
                     let _ = sender.send(mime_types);
                 }
@@ -227,6 +307,8 @@ impl Dispatch<ZwlrDataControlDeviceV1, String> for State {
                 }
                 info.primary_offer = source.map(|source| OfferInfo::Runtime { source });
 
+                state.broadcast_selection(dhandle, name, true);
+
                 if let Some(sender) = &state.selection_updated_sender {
                     let _ = sender.send(mime_types);
                 }
@@ -258,6 +340,10 @@ impl Dispatch<ZwlrDataControlOfferV1, (String, bool)> for State {
                 OfferInfo::Buffered { data } => {
                     let mut write = PipeWriter::from(fd);
                     let _ = write.write_all(&data[mime_type.as_str()]);
+
+                    if state.stall_transfers {
+                        state.stalled_fds.push(Arc::new(write.into()));
+                    }
                 }
                 OfferInfo::Runtime { source } => {
                     if state.set_nonblock_on_write_fd {
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use wayland_protocols::ext::data_control::v1::server::ext_data_control_device_v1::ExtDataControlDeviceV1;
 use wayland_protocols::ext::data_control::v1::server::ext_data_control_manager_v1::{
     self, ExtDataControlManagerV1,
@@ -9,6 +11,7 @@ use wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_manager_v
 use wayland_server::protocol::wl_seat::WlSeat;
 use wayland_server::Dispatch;
 
+use crate::tests::state::{self, OfferInfo};
 use crate::tests::TestServer;
 use crate::utils::*;
 use crate::{server_ignore_global_impl, server_ignore_impl};
@@ -265,3 +268,203 @@ fn is_primary_selection_supported_data_control_v1_and_ext_data_control() {
     let result = is_primary_selection_supported_internal(Some(socket_name)).unwrap();
     assert!(result);
 }
+
+#[test]
+fn mime_type_matches_test() {
+    assert!(mime_type_matches("text/plain", "text/plain"));
+    assert!(mime_type_matches("TEXT/Plain", "text/plain"));
+    assert!(mime_type_matches("*/*", "application/json"));
+    assert!(mime_type_matches("image/*", "image/png"));
+    assert!(!mime_type_matches("image/*", "text/png"));
+    assert!(!mime_type_matches("*/png", "image/jpeg"));
+
+    assert!(mime_type_matches(
+        "text/plain;charset=utf-8",
+        "text/plain ; Charset=\"UTF-8\""
+    ));
+    assert!(mime_type_matches(
+        "text/*;charset=utf-8",
+        "text/html;charset=utf-8"
+    ));
+    assert!(!mime_type_matches(
+        "text/*;charset=utf-8",
+        "text/html;charset=iso-8859-1"
+    ));
+    assert!(!mime_type_matches("text/*;charset=utf-8", "text/html"));
+    assert!(!mime_type_matches("text/html;level=A", "text/html;level=a"));
+
+    assert!(mime_type_matches("UTF8_STRING", "UTF8_STRING"));
+    assert!(!mime_type_matches("utf8_string", "UTF8_STRING"));
+    assert!(!mime_type_matches("*/*", "STRING"));
+}
+
+#[test]
+fn list_seats_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<state::State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = state::State {
+        seats: HashMap::from([
+            (
+                "seat0".into(),
+                state::SeatInfo {
+                    offer: Some(OfferInfo::Buffered {
+                        data: HashMap::from([("b".into(), vec![]), ("a".into(), vec![])]),
+                    }),
+                    ..Default::default()
+                },
+            ),
+            (
+                "seat1".into(),
+                state::SeatInfo {
+                    primary_offer: Some(OfferInfo::Buffered {
+                        data: HashMap::from([("c".into(), vec![])]),
+                    }),
+                    ..Default::default()
+                },
+            ),
+        ]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let seats = list_seats_internal(Protocol::Auto, Some(socket_name)).unwrap();
+    assert_eq!(
+        seats,
+        [
+            SeatInfo {
+                name: Some("seat0".into()),
+                has_device: true,
+                selection: Some(vec!["a".into(), "b".into()]),
+                primary_selection: PrimarySelection::Empty,
+            },
+            SeatInfo {
+                name: Some("seat1".into()),
+                has_device: true,
+                selection: None,
+                primary_selection: PrimarySelection::Offered(vec!["c".into()]),
+            },
+        ]
+    );
+}
+
+#[test]
+fn probe_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, WlSeat, ()>(6, ());
+    server
+        .display
+        .handle()
+        .create_global::<State, WlSeat, ()>(6, ());
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+    server
+        .display
+        .handle()
+        .create_global::<State, ExtDataControlManagerV1, ()>(1, ());
+
+    let state = State {
+        advertise_primary_selection: true,
+    };
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let report = probe_internal(Protocol::Auto, Some(socket_name)).unwrap();
+    assert_eq!(
+        report,
+        ProbeReport {
+            ext_data_control: Some(1),
+            wlr_data_control: Some(2),
+            protocol: Some(DataControlProtocol::Ext),
+            primary_selection: Some(true),
+            seats: 2,
+        }
+    );
+}
+
+#[test]
+fn probe_prefer_wlr_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, WlSeat, ()>(6, ());
+    server
+        .display
+        .handle()
+        .create_global::<State, WlSeat, ()>(6, ());
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+    server
+        .display
+        .handle()
+        .create_global::<State, ExtDataControlManagerV1, ()>(1, ());
+
+    let state = State {
+        advertise_primary_selection: true,
+    };
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let report = probe_internal(Protocol::PreferWlr, Some(socket_name)).unwrap();
+    assert_eq!(
+        report,
+        ProbeReport {
+            ext_data_control: Some(1),
+            wlr_data_control: Some(2),
+            protocol: Some(DataControlProtocol::Wlr),
+            primary_selection: Some(true),
+            seats: 2,
+        }
+    );
+}
+
+#[test]
+fn probe_no_data_control() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, WlSeat, ()>(6, ());
+
+    let state = State {
+        advertise_primary_selection: false,
+    };
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let report = probe_internal(Protocol::Auto, Some(socket_name)).unwrap();
+    assert_eq!(
+        report,
+        ProbeReport {
+            seats: 1,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn parse_protocol_test() {
+    assert_eq!("auto".parse(), Ok(Protocol::Auto));
+    assert_eq!("ext-only".parse(), Ok(Protocol::ExtOnly));
+    assert_eq!("wlr-only".parse(), Ok(Protocol::WlrOnly));
+    assert_eq!("prefer-wlr".parse(), Ok(Protocol::PreferWlr));
+    assert!("wlr".parse::<Protocol>().is_err());
+    assert!("".parse::<Protocol>().is_err());
+}
@@ -2,7 +2,7 @@
 
 use std::ffi::OsString;
 use std::os::unix::net::UnixStream;
-use std::path::PathBuf;
+use std::str::FromStr;
 use std::{env, io};
 
 use wayland_client::protocol::wl_registry::{self, WlRegistry};
@@ -13,9 +13,11 @@ use wayland_client::{
 use wayland_protocols::ext::data_control::v1::client::ext_data_control_manager_v1::ExtDataControlManagerV1;
 use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
 
+use crate::common::{self, connect, initialize_with, socket_path};
 use crate::data_control::{
-    impl_dispatch_device, impl_dispatch_manager, impl_dispatch_offer, Manager,
+    self, impl_dispatch_device, impl_dispatch_manager, impl_dispatch_offer, Manager,
 };
+use crate::paste::{self, roundtrip_until};
 
 /// Checks if the given MIME type represents plain text.
 ///
@@ -35,15 +37,105 @@ pub fn is_text(mime_type: &str) -> bool {
     }
 }
 
-struct PrimarySelectionState {
+/// Checks if the MIME type matches the pattern.
+///
+/// The pattern is a MIME type where the type or the subtype can be a `*` wildcard, like `image/*`
+/// or `*/*`. The comparison ignores case and whitespace, as well as the case of the `charset`
+/// parameter value. Parameters of the pattern must be present in the MIME type, while other
+/// parameters of the MIME type are ignored.
+///
+/// Names that aren't MIME types, like `UTF8_STRING`, only match themselves.
+///
+/// # Examples
+///
+/// ```
+/// use wl_clipboard_rs::utils::mime_type_matches;
+///
+/// assert!(mime_type_matches("image/*", "image/png"));
+/// assert!(mime_type_matches("text/*;charset=utf-8", "text/plain; charset=UTF-8"));
+/// assert!(mime_type_matches("text/plain", "text/plain;charset=utf-8"));
+/// assert!(!mime_type_matches("text/plain;charset=utf-8", "text/plain"));
+/// assert!(!mime_type_matches("*/*", "UTF8_STRING"));
+/// ```
+pub fn mime_type_matches(pattern: &str, mime_type: &str) -> bool {
+    if pattern == mime_type {
+        return true;
+    }
+
+    let (Some(pattern), Some(mime_type)) = (
+        ParsedMimeType::parse(pattern),
+        ParsedMimeType::parse(mime_type),
+    ) else {
+        return false;
+    };
+
+    (pattern.type_ == "*" || pattern.type_ == mime_type.type_)
+        && (pattern.subtype == "*" || pattern.subtype == mime_type.subtype)
+        && pattern
+            .params
+            .iter()
+            .all(|param| mime_type.params.contains(param))
+}
+
+// MIME type split into parts, normalized for comparison.
+struct ParsedMimeType {
+    type_: String,
+    subtype: String,
+    params: Vec<(String, String)>,
+}
+
+impl ParsedMimeType {
+    fn parse(mime_type: &str) -> Option<Self> {
+        let mut parts = mime_type.split(';');
+
+        let (type_, subtype) = parts.next()?.split_once('/')?;
+        let (type_, subtype) = (type_.trim(), subtype.trim());
+        if type_.is_empty() || subtype.is_empty() {
+            return None;
+        }
+
+        let params = parts
+            .map(str::trim)
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (name, value) = param.split_once('=')?;
+                let name = name.trim().to_ascii_lowercase();
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+
+                // Charset names are case-insensitive, unlike the values of other parameters.
+                let value = if name == "charset" {
+                    value.to_ascii_lowercase()
+                } else {
+                    value.to_owned()
+                };
+
+                Some((name, value))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            params,
+        })
+    }
+}
+
+struct ProbeState {
     // Any seat that we get from the compositor.
     seat: Option<WlSeat>,
-    clipboard_manager: Option<Manager>,
-    saw_zwlr_v1: bool,
+    seat_count: usize,
+    // Global names and versions of the data-control managers.
+    ext_manager: Option<(u32, u32)>,
+    wlr_manager: Option<(u32, u32)>,
     got_primary_selection: bool,
 }
 
-impl Dispatch<WlRegistry, ()> for PrimarySelectionState {
+impl Dispatch<WlRegistry, ()> for ProbeState {
     fn event(
         state: &mut Self,
         registry: &WlRegistry,
@@ -58,31 +150,27 @@ impl Dispatch<WlRegistry, ()> for PrimarySelectionState {
             version,
         } = event
         {
-            if interface == WlSeat::interface().name && version >= 2 && state.seat.is_none() {
-                let seat = registry.bind(name, 2, qh, ());
-                state.seat = Some(seat);
-            }
+            if interface == WlSeat::interface().name && version >= 2 {
+                state.seat_count += 1;
 
-            if state.clipboard_manager.is_none() {
-                if interface == ZwlrDataControlManagerV1::interface().name {
-                    if version == 1 {
-                        state.saw_zwlr_v1 = true;
-                    } else {
-                        let manager = registry.bind(name, 2, qh, ());
-                        state.clipboard_manager = Some(Manager::Zwlr(manager));
-                    }
+                if state.seat.is_none() {
+                    let seat = registry.bind(name, 2, qh, ());
+                    state.seat = Some(seat);
                 }
+            }
 
-                if interface == ExtDataControlManagerV1::interface().name {
-                    let manager = registry.bind(name, 1, qh, ());
-                    state.clipboard_manager = Some(Manager::Ext(manager));
-                }
+            if interface == ZwlrDataControlManagerV1::interface().name {
+                state.wlr_manager = Some((name, version));
+            }
+
+            if interface == ExtDataControlManagerV1::interface().name {
+                state.ext_manager = Some((name, version));
             }
         }
     }
 }
 
-impl Dispatch<WlSeat, ()> for PrimarySelectionState {
+impl Dispatch<WlSeat, ()> for ProbeState {
     fn event(
         _state: &mut Self,
         _proxy: &WlSeat,
@@ -94,15 +182,15 @@ impl Dispatch<WlSeat, ()> for PrimarySelectionState {
     }
 }
 
-impl_dispatch_manager!(PrimarySelectionState);
+impl_dispatch_manager!(ProbeState);
 
-impl_dispatch_device!(PrimarySelectionState, (), |state: &mut Self, event, _| {
+impl_dispatch_device!(ProbeState, (), |state: &mut Self, event, _| {
     if let Event::PrimarySelection { id: _ } = event {
         state.got_primary_selection = true;
     }
 });
 
-impl_dispatch_offer!(PrimarySelectionState);
+impl_dispatch_offer!(ProbeState);
 
 /// Errors that can occur when checking whether the primary selection is supported.
 #[derive(thiserror::Error, Debug)]
@@ -168,66 +256,393 @@ pub fn is_primary_selection_supported() -> Result<bool, PrimarySelectionCheckErr
 pub(crate) fn is_primary_selection_supported_internal(
     socket_name: Option<OsString>,
 ) -> Result<bool, PrimarySelectionCheckError> {
+    let report = probe_internal(Protocol::Auto, socket_name).map_err(|err| match err {
+        ProbeError::SocketOpenError(err) => PrimarySelectionCheckError::SocketOpenError(err),
+        ProbeError::WaylandConnection(err) => PrimarySelectionCheckError::WaylandConnection(err),
+        ProbeError::WaylandCommunication(err) => {
+            PrimarySelectionCheckError::WaylandCommunication(err)
+        }
+    })?;
+
+    match report.primary_selection {
+        Some(supported) => Ok(supported),
+        None if report.protocol.is_none() => Err(PrimarySelectionCheckError::MissingProtocol),
+        None => Err(PrimarySelectionCheckError::NoSeats),
+    }
+}
+
+/// Environment variable for picking the data-control protocol, read by [`Protocol::from_env()`].
+pub const PROTOCOL_ENV_VAR: &str = "WL_CLIPBOARD_RS_PROTOCOL";
+
+/// Preference between the data-control protocols.
+///
+/// Both ext-data-control and wlr-data-control give access to the clipboard. By default
+/// ext-data-control is preferred, but a compositor's implementation of either may be buggy, in
+/// which case the other one can be picked instead.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord, Default)]
+pub enum Protocol {
+    /// Use ext-data-control if available, otherwise wlr-data-control.
+    #[default]
+    Auto,
+    /// Use only ext-data-control.
+    ExtOnly,
+    /// Use only wlr-data-control.
+    WlrOnly,
+    /// Use wlr-data-control if available, otherwise ext-data-control.
+    PreferWlr,
+}
+
+/// Error returned when parsing an invalid [`Protocol`].
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+#[error("Invalid protocol {0:?}, expected one of auto, ext-only, wlr-only, prefer-wlr")]
+pub struct ParseProtocolError(String);
+
+impl FromStr for Protocol {
+    type Err = ParseProtocolError;
+
+    /// Parses one of `auto`, `ext-only`, `wlr-only` or `prefer-wlr`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Protocol::Auto),
+            "ext-only" => Ok(Protocol::ExtOnly),
+            "wlr-only" => Ok(Protocol::WlrOnly),
+            "prefer-wlr" => Ok(Protocol::PreferWlr),
+            _ => Err(ParseProtocolError(s.to_owned())),
+        }
+    }
+}
+
+impl Protocol {
+    /// Reads the protocol preference from the `WL_CLIPBOARD_RS_PROTOCOL` environment variable.
+    ///
+    /// Returns [`Protocol::Auto`] if the variable is unset or empty. The accepted values are the
+    /// same as for parsing with [`str::parse()`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate wl_clipboard_rs;
+    /// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
+    /// use wl_clipboard_rs::copy::Options;
+    /// use wl_clipboard_rs::utils::Protocol;
+    ///
+    /// let mut opts = Options::new();
+    /// opts.protocol(Protocol::from_env()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_env() -> Result<Self, ParseProtocolError> {
+        match env::var(PROTOCOL_ENV_VAR) {
+            Ok(value) if !value.is_empty() => value.parse(),
+            Ok(_) | Err(env::VarError::NotPresent) => Ok(Protocol::Auto),
+            Err(env::VarError::NotUnicode(value)) => {
+                Err(ParseProtocolError(value.to_string_lossy().into_owned()))
+            }
+        }
+    }
+}
+
+// Describes the data-control protocol requested with `protocol`, for error messages.
+pub(crate) fn describe_protocol(protocol: Protocol, wlr_version: u32) -> String {
+    let wlr = format!("wlr-data-control version {wlr_version}");
+    match protocol {
+        Protocol::Auto => format!("ext-data-control, or {wlr}"),
+        Protocol::ExtOnly => "ext-data-control".to_owned(),
+        Protocol::WlrOnly => wlr,
+        Protocol::PreferWlr => format!("{wlr}, or ext-data-control"),
+    }
+}
+
+/// Data-control protocol used for accessing the clipboard.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum DataControlProtocol {
+    /// The ext-data-control protocol.
+    Ext,
+    /// The wlr-data-control protocol.
+    Wlr,
+}
+
+impl std::fmt::Display for DataControlProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataControlProtocol::Ext => f.write_str("ext-data-control"),
+            DataControlProtocol::Wlr => f.write_str("wlr-data-control"),
+        }
+    }
+}
+
+/// Report of the clipboard capabilities of the compositor, returned by [`probe()`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ProbeReport {
+    /// Version of ext-data-control advertised by the compositor, `None` if it's not advertised.
+    pub ext_data_control: Option<u32>,
+
+    /// Version of wlr-data-control advertised by the compositor, `None` if it's not advertised.
+    pub wlr_data_control: Option<u32>,
+
+    /// Protocol that would be used for accessing the clipboard, `None` if none of the requested
+    /// protocols is advertised.
+    pub protocol: Option<DataControlProtocol>,
+
+    /// Whether the primary selection is supported.
+    ///
+    /// `None` if it can't be determined: checking requires the data-control protocol and at least
+    /// one seat.
+    pub primary_selection: Option<bool>,
+
+    /// Number of seats.
+    pub seats: usize,
+}
+
+/// Errors that can occur when probing the compositor.
+#[derive(thiserror::Error, Debug)]
+pub enum ProbeError {
+    #[error("Couldn't open the provided Wayland socket")]
+    SocketOpenError(#[source] io::Error),
+
+    #[error("Couldn't connect to the Wayland compositor")]
+    WaylandConnection(#[source] ConnectError),
+
+    #[error("Wayland compositor communication error")]
+    WaylandCommunication(#[source] DispatchError),
+}
+
+/// Checks what the compositor offers for accessing the clipboard.
+///
+/// Unlike the other functions, this doesn't fail if the data-control protocol is missing, which
+/// makes it useful for diagnostics. `protocol` is the data-control protocol that would be
+/// requested, see [`Protocol`]; the report says which one it resolves to.
+///
+/// # Examples
+///
+/// ```no_run
+/// # extern crate wl_clipboard_rs;
+/// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// use wl_clipboard_rs::utils::{probe, Protocol};
+///
+/// let report = probe(Protocol::Auto)?;
+/// match report.protocol {
+///     Some(protocol) => println!("Using {protocol}"),
+///     None => println!("The clipboard is inaccessible"),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[inline]
+pub fn probe(protocol: Protocol) -> Result<ProbeReport, ProbeError> {
+    probe_internal(protocol, None)
+}
+
+// The internal function accepts the socket name, used for tests.
+pub(crate) fn probe_internal(
+    protocol: Protocol,
+    socket_name: Option<OsString>,
+) -> Result<ProbeReport, ProbeError> {
     // Connect to the Wayland compositor.
     let conn = match socket_name {
         Some(name) => {
-            let mut socket_path = env::var_os("XDG_RUNTIME_DIR")
-                .map(Into::<PathBuf>::into)
-                .ok_or(ConnectError::NoCompositor)
-                .map_err(PrimarySelectionCheckError::WaylandConnection)?;
-            if !socket_path.is_absolute() {
-                return Err(PrimarySelectionCheckError::WaylandConnection(
-                    ConnectError::NoCompositor,
-                ));
-            }
-            socket_path.push(name);
-
-            let stream = UnixStream::connect(socket_path)
-                .map_err(PrimarySelectionCheckError::SocketOpenError)?;
+            let socket_path = socket_path(name).map_err(ProbeError::WaylandConnection)?;
+            let stream = UnixStream::connect(socket_path).map_err(ProbeError::SocketOpenError)?;
             Connection::from_socket(stream)
         }
         None => Connection::connect_to_env(),
     }
-    .map_err(PrimarySelectionCheckError::WaylandConnection)?;
+    .map_err(ProbeError::WaylandConnection)?;
     let display = conn.display();
 
     let mut queue = conn.new_event_queue();
     let qh = queue.handle();
 
-    let mut state = PrimarySelectionState {
+    let mut state = ProbeState {
         seat: None,
-        clipboard_manager: None,
-        saw_zwlr_v1: false,
+        seat_count: 0,
+        ext_manager: None,
+        wlr_manager: None,
         got_primary_selection: false,
     };
 
     // Retrieve the global interfaces.
-    let _registry = display.get_registry(&qh, ());
+    let registry = display.get_registry(&qh, ());
     queue
         .roundtrip(&mut state)
-        .map_err(PrimarySelectionCheckError::WaylandCommunication)?;
+        .map_err(ProbeError::WaylandCommunication)?;
 
-    // If data control is present but is version 1, then return false as version 1 does not support
-    // primary clipboard.
-    if state.clipboard_manager.is_none() && state.saw_zwlr_v1 {
-        return Ok(false);
-    }
+    let mut report = ProbeReport {
+        ext_data_control: state.ext_manager.map(|(_, version)| version),
+        wlr_data_control: state.wlr_manager.map(|(_, version)| version),
+        seats: state.seat_count,
+        ..Default::default()
+    };
+
+    // Pick the manager like the rest of the crate does.
+    let ext = state.ext_manager.map(|_| DataControlProtocol::Ext);
+    let wlr = state.wlr_manager.map(|_| DataControlProtocol::Wlr);
+    report.protocol = match protocol {
+        Protocol::Auto => ext.or(wlr),
+        Protocol::ExtOnly => ext,
+        Protocol::WlrOnly => wlr,
+        Protocol::PreferWlr => wlr.or(ext),
+    };
+
+    let clipboard_manager = match (report.protocol, state.ext_manager, state.wlr_manager) {
+        (Some(DataControlProtocol::Ext), Some((name, _)), _) => {
+            Manager::Ext(registry.bind(name, 1, &qh, ()))
+        }
+        (Some(DataControlProtocol::Wlr), _, Some((name, version))) => {
+            // Version 1 does not support the primary selection.
+            if version == 1 {
+                report.primary_selection = Some(false);
+                return Ok(report);
+            }
 
-    // Verify that we got the clipboard manager.
-    let Some(ref clipboard_manager) = state.clipboard_manager else {
-        return Err(PrimarySelectionCheckError::MissingProtocol);
+            Manager::Zwlr(registry.bind(name, 2, &qh, ()))
+        }
+        _ => return Ok(report),
     };
 
-    // Check if there are no seats.
+    // Checking for the primary selection requires a seat.
     let Some(ref seat) = state.seat else {
-        return Err(PrimarySelectionCheckError::NoSeats);
+        return Ok(report);
     };
 
     clipboard_manager.get_data_device(seat, &qh, ());
 
     queue
         .roundtrip(&mut state)
-        .map_err(PrimarySelectionCheckError::WaylandCommunication)?;
+        .map_err(ProbeError::WaylandCommunication)?;
+
+    report.primary_selection = Some(state.got_primary_selection);
+    Ok(report)
+}
+
+/// Information about a seat, returned by [`list_seats()`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SeatInfo {
+    /// Name of the seat, for use with [`paste::Seat::Specific`] and [`copy::Seat::Specific`].
+    ///
+    /// `None` if the compositor didn't send the name.
+    ///
+    /// [`copy::Seat::Specific`]: crate::copy::Seat::Specific
+    pub name: Option<String>,
+
+    /// Whether the seat has a data-control device, which is needed for working with its clipboard.
+    ///
+    /// `false` if the compositor took the device away.
+    pub has_device: bool,
+
+    /// MIME types of the regular selection in the order they were offered, `None` if the
+    /// selection is empty.
+    pub selection: Option<Vec<String>>,
+
+    /// Contents of the primary selection.
+    pub primary_selection: PrimarySelection,
+}
+
+/// Contents of the primary selection of a seat, part of [`SeatInfo`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum PrimarySelection {
+    /// The compositor doesn't support the primary selection.
+    Unsupported,
+    /// The primary selection is empty.
+    Empty,
+    /// MIME types of the primary selection in the order they were offered.
+    Offered(Vec<String>),
+}
+
+/// Errors that can occur when listing the seats.
+#[derive(thiserror::Error, Debug)]
+pub enum SeatListError {
+    #[error("Couldn't open the provided Wayland socket")]
+    SocketOpenError(#[source] io::Error),
+
+    #[error("Couldn't connect to the Wayland compositor")]
+    WaylandConnection(#[source] ConnectError),
+
+    #[error("Wayland compositor communication error")]
+    WaylandCommunication(#[source] DispatchError),
+
+    #[error(
+        "A required Wayland protocol ({}) is not supported by the compositor",
+        describe_protocol(*.protocol, 1)
+    )]
+    MissingProtocol { protocol: Protocol },
+}
+
+impl From<common::Error> for SeatListError {
+    fn from(x: common::Error) -> Self {
+        use common::Error::*;
+
+        match x {
+            SocketOpenError(err) => SeatListError::SocketOpenError(err),
+            WaylandConnection(err) => SeatListError::WaylandConnection(err),
+            WaylandCommunication(err) => SeatListError::WaylandCommunication(err.into()),
+            MissingProtocol { protocol, .. } => SeatListError::MissingProtocol { protocol },
+        }
+    }
+}
+
+/// Lists the seats along with their clipboard contents.
+///
+/// The seats are sorted by name. `protocol` picks the data-control protocol to use, see
+/// [`Protocol`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # extern crate wl_clipboard_rs;
+/// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// use wl_clipboard_rs::utils::{list_seats, Protocol};
+///
+/// for seat in list_seats(Protocol::Auto)? {
+///     if let Some(name) = seat.name {
+///         println!("{name}");
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[inline]
+pub fn list_seats(protocol: Protocol) -> Result<Vec<SeatInfo>, SeatListError> {
+    list_seats_internal(protocol, None)
+}
+
+// The internal function accepts the socket name, used for tests.
+pub(crate) fn list_seats_internal(
+    protocol: Protocol,
+    socket_name: Option<OsString>,
+) -> Result<Vec<SeatInfo>, SeatListError> {
+    let conn = connect(socket_name)?;
+    // Accept wlr-data-control version 1 too: the primary selection is reported as unsupported.
+    let (mut queue, common) = initialize_with::<paste::State>(&conn, protocol, 1..=2)?;
+    let mut state = paste::State::new(common, &queue.handle());
+
+    // Retrieve all seat names and offers. The devices that get finished meanwhile are dropped.
+    roundtrip_until(&conn, &mut queue, &mut state, None)
+        .map_err(SeatListError::WaylandCommunication)?;
+
+    let mime_types = |offer: &Option<data_control::Offer>| {
+        offer
+            .as_ref()
+            .map(|offer| state.offers.get(offer).cloned().unwrap_or_default())
+    };
+
+    let mut seats = state
+        .common
+        .seats
+        .values()
+        .map(|data| SeatInfo {
+            name: data.name.clone(),
+            has_device: data.device.is_some(),
+            selection: mime_types(&data.offer),
+            primary_selection: if !state.got_primary_selection {
+                PrimarySelection::Unsupported
+            } else {
+                mime_types(&data.primary_offer)
+                    .map_or(PrimarySelection::Empty, PrimarySelection::Offered)
+            },
+        })
+        .collect::<Vec<_>>();
+    seats.sort_by(|a, b| a.name.cmp(&b.name));
 
-    Ok(state.got_primary_selection)
+    Ok(seats)
 }
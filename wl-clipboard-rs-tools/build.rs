@@ -1,3 +1,18 @@
+#[path = "src/parse.rs"]
+mod parse;
+
+#[path = "src/wl_clip_history.rs"]
+mod wl_clip_history;
+
+#[path = "src/wl_clip_historyd.rs"]
+mod wl_clip_historyd;
+
+#[path = "src/wl_clip_persist.rs"]
+mod wl_clip_persist;
+
+#[path = "src/wl_clip_probe.rs"]
+mod wl_clip_probe;
+
 #[path = "src/wl_copy.rs"]
 mod wl_copy;
 
@@ -51,4 +66,8 @@ fn generate(name: &str, mut cmd: Command) {
 fn main() {
     generate("wl-copy", wl_copy::Options::command());
     generate("wl-paste", wl_paste::Options::command());
+    generate("wl-clip-persist", wl_clip_persist::Options::command());
+    generate("wl-clip-historyd", wl_clip_historyd::Options::command());
+    generate("wl-clip-history", wl_clip_history::Options::command());
+    generate("wl-clip-probe", wl_clip_probe::Options::command());
 }
@@ -0,0 +1,61 @@
+#![deny(unsafe_code)]
+
+use std::io::{stdout, Write};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use wl_clipboard_rs::utils::is_text;
+use wl_clipboard_rs_tools::history::{self, Client};
+use wl_clipboard_rs_tools::wl_clip_history::{Command, Options};
+
+fn main() -> Result<(), anyhow::Error> {
+    // Parse command-line options.
+    let options = Options::parse();
+
+    let Some(socket_path) = options.socket.or_else(history::default_socket_path) else {
+        bail!("Couldn't determine the socket path, specify it with --socket");
+    };
+    let client = Client::new(socket_path);
+
+    match options.command {
+        Command::List => {
+            for entry in client.list()? {
+                println!(
+                    "{}\t{}\t{}",
+                    entry.id,
+                    entry.size,
+                    entry.mime_types.join(" ")
+                );
+            }
+        }
+        Command::Get { id, mime_type } => {
+            let mime_type = match mime_type {
+                Some(mime_type) => mime_type,
+                None => {
+                    let entry = client
+                        .list()?
+                        .into_iter()
+                        .find(|entry| entry.id == id)
+                        .with_context(|| format!("History entry {id} not found"))?;
+                    let mut mime_types = entry.mime_types.into_iter();
+                    let first = mime_types.clone().next();
+                    match mime_types.find(|mime_type| is_text(mime_type)).or(first) {
+                        Some(mime_type) => mime_type,
+                        None => bail!("History entry {id} is empty"),
+                    }
+                }
+            };
+
+            let contents = client.get(id, &mime_type)?;
+            stdout()
+                .lock()
+                .write_all(&contents)
+                .context("Couldn't write contents to stdout")?;
+        }
+        Command::Select { id } => client.select(id)?,
+        Command::Delete { id } => client.delete(id)?,
+        Command::Clear => client.clear()?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+#![deny(unsafe_code)]
+
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use log::info;
+use wl_clipboard_rs::paste::{self, ClipboardType};
+use wl_clipboard_rs::utils::Protocol;
+use wl_clipboard_rs_tools::history::{self, Store};
+use wl_clipboard_rs_tools::wl_clip_historyd::Options;
+
+fn main() -> Result<(), anyhow::Error> {
+    // Parse command-line options.
+    let options = Options::parse();
+
+    stderrlog::new()
+        .verbosity(usize::from(options.verbose) + 1)
+        .init()
+        .unwrap();
+
+    let Some(store_dir) = options.store.or_else(history::default_store_dir) else {
+        bail!("Couldn't determine the store directory, specify it with --store");
+    };
+    let Some(socket_path) = options.socket.or_else(history::default_socket_path) else {
+        bail!("Couldn't determine the socket path, specify it with --socket");
+    };
+
+    let store = Store::open(&store_dir, options.max_entries)
+        .with_context(|| format!("Couldn't open the store in {}", store_dir.display()))?;
+
+    // Remove the socket left over by a previous daemon, unless that daemon is still running.
+    if UnixStream::connect(&socket_path).is_ok() {
+        bail!(
+            "Another daemon is already listening on {}",
+            socket_path.display()
+        );
+    }
+    match fs::remove_file(&socket_path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => {
+            return Err(err).context("Couldn't remove the stale socket");
+        }
+        _ => (),
+    }
+    let listener = UnixListener::bind(&socket_path)
+        .with_context(|| format!("Couldn't listen on {}", socket_path.display()))?;
+    info!("Listening on {}", socket_path.display());
+
+    let mut paste_options = paste::Options::new();
+    paste_options.timeout(options.timeout);
+    if let Some(max_size) = options.max_size {
+        paste_options.max_size(max_size);
+    }
+
+    let mut history_options = history::Options::new();
+    history_options
+        .clipboard(if options.primary {
+            ClipboardType::Primary
+        } else {
+            ClipboardType::Regular
+        })
+        .protocol(Protocol::from_env()?)
+        .paste_options(paste_options);
+    if let Some(seat) = options.seat {
+        history_options.seat(seat);
+    }
+
+    let result = history_options.serve(store, listener);
+    drop(fs::remove_file(&socket_path));
+    result?;
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+#![deny(unsafe_code)]
+
+use clap::Parser;
+use log::{info, trace, warn};
+use wl_clipboard_rs::copy::{self, CopyHandle, MimeSource, MimeType, Source};
+use wl_clipboard_rs::paste::{self, ClipboardType, Error, Seat};
+use wl_clipboard_rs::snapshot::Snapshot;
+use wl_clipboard_rs::utils::Protocol;
+use wl_clipboard_rs_tools::wl_clip_persist::Options;
+
+/// MIME type offered along with the kept contents, to recognize them when they come back.
+const MARKER_MIME_TYPE: &str = "application/x-wl-clip-persist";
+
+fn main() -> Result<(), anyhow::Error> {
+    // Parse command-line options.
+    let options = Options::parse();
+
+    stderrlog::new()
+        .verbosity(usize::from(options.verbose) + 1)
+        .init()
+        .unwrap();
+
+    let clipboard = if options.primary {
+        ClipboardType::Primary
+    } else {
+        ClipboardType::Regular
+    };
+    let seat = options
+        .seat
+        .as_ref()
+        .map(|x| Seat::Specific(x))
+        .unwrap_or_default();
+
+    let protocol = Protocol::from_env()?;
+
+    let mut paste_options = paste::Options::new();
+    paste_options
+        .protocol(protocol)
+        .timeout(options.timeout)
+        .deny_mime_types(options.deny_type.iter().cloned().collect());
+    if !options.allow_type.is_empty() {
+        paste_options.allow_mime_types(options.allow_type.iter().cloned().collect());
+    }
+    if let Some(max_size) = options.max_size {
+        paste_options.max_size(max_size);
+    }
+
+    let mut copy_options = copy::Options::new();
+    copy_options
+        .clipboard(if options.primary {
+            copy::ClipboardType::Primary
+        } else {
+            copy::ClipboardType::Regular
+        })
+        .seat(
+            options
+                .seat
+                .clone()
+                .map(copy::Seat::Specific)
+                .unwrap_or_default(),
+        )
+        // The snapshot has exactly the MIME types to offer.
+        .omit_additional_text_mime_types(true)
+        .protocol(protocol);
+
+    // Serving of the kept contents, stops by itself once the clipboard changes.
+    let mut handle: Option<CopyHandle> = None;
+
+    for offer in paste_options.clone().watch(clipboard, seat)? {
+        let Some(offer) = offer? else {
+            trace!("Clipboard cleared");
+            continue;
+        };
+
+        if offer.mime_types().iter().any(|x| x == MARKER_MIME_TYPE) {
+            trace!("Clipboard holds the kept contents");
+            continue;
+        }
+
+        let contents = match offer.receive_all(paste_options.clone()) {
+            Ok(contents) => contents,
+            Err(err @ (Error::Timeout | Error::TooLarge { .. })) => {
+                info!("Leaving the clipboard alone: {}", err);
+                continue;
+            }
+            Err(err) => {
+                warn!("Couldn't read the clipboard contents: {}", err);
+                continue;
+            }
+        };
+
+        if contents.is_empty() {
+            trace!("No MIME types to keep");
+            continue;
+        }
+
+        trace!(
+            "Keeping the clipboard contents in {} MIME types",
+            contents.len()
+        );
+
+        let mut sources = Snapshot { contents }.into_sources();
+        sources.push(MimeSource {
+            source: Source::Bytes(Box::default()),
+            mime_type: MimeType::Specific(MARKER_MIME_TYPE.to_owned()),
+        });
+
+        // A failed copy, e.g. because the seat went away, keeps the previous one and the watching
+        // going.
+        let new_handle = match copy_options.clone().copy_multi(sources) {
+            Ok(new_handle) => new_handle,
+            Err(err) => {
+                warn!("Couldn't keep the clipboard contents: {}", err);
+                continue;
+            }
+        };
+
+        // The compositor only cancels the previous copy on the watched seat, stop serving it on
+        // the other seats now that the new one took over.
+        if let Some(handle) = handle.replace(new_handle) {
+            handle.cancel();
+            drop(handle.join());
+        }
+    }
+
+    Ok(())
+}
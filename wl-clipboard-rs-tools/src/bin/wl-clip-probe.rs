@@ -0,0 +1,54 @@
+#![deny(unsafe_code)]
+
+use std::env;
+
+use clap::Parser;
+use wl_clipboard_rs::utils::{probe, Protocol, PROTOCOL_ENV_VAR};
+use wl_clipboard_rs_tools::wl_clip_probe::Options;
+
+fn version(version: Option<u32>) -> String {
+    match version {
+        Some(version) => format!("version {version}"),
+        None => "not advertised".to_owned(),
+    }
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    // Parse command-line options.
+    Options::parse();
+
+    println!("wl-clipboard-rs version: {}", env!("CARGO_PKG_VERSION"));
+
+    let display = env::var("WAYLAND_DISPLAY");
+    println!(
+        "WAYLAND_DISPLAY: {}",
+        display.as_deref().unwrap_or("not set")
+    );
+
+    let protocol = env::var(PROTOCOL_ENV_VAR);
+    println!(
+        "{PROTOCOL_ENV_VAR}: {}",
+        protocol.as_deref().unwrap_or("not set")
+    );
+
+    let report = probe(Protocol::from_env()?)?;
+
+    println!("ext-data-control: {}", version(report.ext_data_control));
+    println!("wlr-data-control: {}", version(report.wlr_data_control));
+
+    match report.protocol {
+        Some(protocol) => println!("protocol in use: {protocol}"),
+        None => println!("protocol in use: none, the clipboard is inaccessible"),
+    }
+
+    let primary_selection = match report.primary_selection {
+        Some(true) => "supported",
+        Some(false) => "unsupported",
+        None => "unknown",
+    };
+    println!("primary selection: {primary_selection}");
+
+    println!("seats: {}", report.seats);
+
+    Ok(())
+}
@@ -8,8 +8,8 @@ use anyhow::{Context, Error};
 use libc::fork;
 use rustix::stdio::{dup2_stdin, dup2_stdout};
 use wl_clipboard_rs::copy::{self, ServeRequests, Source};
-use wl_clipboard_rs::paste::{self, get_contents};
-use wl_clipboard_rs::utils::is_text;
+use wl_clipboard_rs::paste;
+use wl_clipboard_rs::utils::{is_text, Protocol};
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 enum Verbosity {
@@ -219,6 +219,8 @@ fn main() -> Result<(), Error> {
         .init()
         .unwrap();
 
+    let protocol = Protocol::from_env()?;
+
     if options.out {
         // Paste.
         let mime_type = match options.target.as_ref() {
@@ -232,8 +234,10 @@ fn main() -> Result<(), Error> {
             paste::ClipboardType::Regular
         };
 
+        let mut paste_options = paste::Options::new();
+        paste_options.protocol(protocol);
         let (mut read, mime_type) =
-            get_contents(clipboard_type, paste::Seat::Unspecified, mime_type)?;
+            paste_options.get_contents(clipboard_type, paste::Seat::Unspecified, mime_type)?;
 
         // Read the contents.
         let mut contents = vec![];
@@ -282,7 +286,9 @@ fn main() -> Result<(), Error> {
 
         let foreground = options.verbosity != Verbosity::Silent;
 
-        let prepared_copy = copy::Options::from(options).prepare_copy(source, mime_type)?;
+        let mut copy_options = copy::Options::from(options);
+        copy_options.protocol(protocol);
+        let prepared_copy = copy_options.prepare_copy(source, mime_type)?;
 
         if foreground {
             prepared_copy.serve()?;
@@ -1,13 +1,16 @@
 use std::fs::OpenOptions;
 use std::os::unix::ffi::OsStringExt;
 
+use anyhow::Context;
 use clap::Parser;
 use libc::fork;
 use rustix::stdio::{dup2_stdin, dup2_stdout};
-use wl_clipboard_rs::copy::{self, clear, ClipboardType, MimeType, Seat, ServeRequests, Source};
+use wl_clipboard_rs::copy::{self, ClipboardType, MimeType, Seat, ServeRequests, Source};
+use wl_clipboard_rs::snapshot::Snapshot;
+use wl_clipboard_rs::utils::Protocol;
 use wl_clipboard_rs_tools::wl_copy::Options;
 
-fn from_options(x: Options) -> wl_clipboard_rs::copy::Options {
+fn from_options(x: Options, protocol: Protocol) -> wl_clipboard_rs::copy::Options {
     let mut opts = copy::Options::new();
     opts.serve_requests(if x.paste_once {
         ServeRequests::Only(1)
@@ -25,7 +28,8 @@ fn from_options(x: Options) -> wl_clipboard_rs::copy::Options {
         ClipboardType::Regular
     })
     .trim_newline(x.trim_newline)
-    .seat(x.seat.map(Seat::Specific).unwrap_or_default());
+    .seat(x.seat.map(Seat::Specific).unwrap_or_default())
+    .protocol(protocol);
     opts
 }
 
@@ -38,16 +42,20 @@ fn main() -> Result<(), anyhow::Error> {
         .init()
         .unwrap();
 
+    let protocol = Protocol::from_env()?;
+
     if options.clear {
         let clipboard = if options.primary {
             ClipboardType::Primary
         } else {
             ClipboardType::Regular
         };
-        clear(
-            clipboard,
-            options.seat.map(Seat::Specific).unwrap_or_default(),
-        )?;
+        let mut copy_options = copy::Options::new();
+        copy_options
+            .clipboard(clipboard)
+            .seat(options.seat.map(Seat::Specific).unwrap_or_default())
+            .protocol(protocol);
+        copy_options.clear()?;
         return Ok(());
     }
 
@@ -80,7 +88,21 @@ fn main() -> Result<(), anyhow::Error> {
     };
 
     let foreground = options.foreground;
-    let prepared_copy = from_options(options).prepare_copy(source, mime_type)?;
+    let prepared_copy = if let Some(path) = options.restore.take() {
+        let snapshot = Snapshot::load(&path).with_context(|| {
+            format!(
+                "Couldn't load the clipboard contents from {}",
+                path.display()
+            )
+        })?;
+
+        // The snapshot has exactly the MIME types to offer.
+        let mut copy_options = from_options(options, protocol);
+        copy_options.omit_additional_text_mime_types(true);
+        copy_options.prepare_copy_multi(snapshot.into_sources())?
+    } else {
+        from_options(options, protocol).prepare_copy(source, mime_type)?
+    };
 
     if foreground {
         prepared_copy.serve()?;
@@ -1,7 +1,9 @@
 #![deny(unsafe_code)]
 
+use std::ffi::OsString;
 use std::fs::read_link;
-use std::io::{stdout, Read, Write};
+use std::io::{stdout, Write};
+use std::process::{Command, Stdio};
 
 use anyhow::Context;
 use clap::Parser;
@@ -9,7 +11,8 @@ use libc::STDOUT_FILENO;
 use log::trace;
 use mime_guess::Mime;
 use wl_clipboard_rs::paste::*;
-use wl_clipboard_rs::utils::is_text;
+use wl_clipboard_rs::snapshot::Snapshot;
+use wl_clipboard_rs::utils::{is_text, list_seats, PrimarySelection, Protocol};
 use wl_clipboard_rs_tools::wl_paste::Options;
 
 fn infer_mime_type() -> Option<Mime> {
@@ -20,6 +23,53 @@ fn infer_mime_type() -> Option<Mime> {
     }
 }
 
+fn watch_clipboard(
+    paste_options: wl_clipboard_rs::paste::Options,
+    clipboard: ClipboardType,
+    seat: Seat<'_>,
+    mime_type: MimeType<'_>,
+    command: &[OsString],
+) -> Result<(), anyhow::Error> {
+    for offer in paste_options.watch(clipboard, seat)? {
+        let mut child = Command::new(&command[0]);
+        child.args(&command[1..]);
+
+        match offer? {
+            Some(offer) => {
+                let read = match offer.receive(mime_type) {
+                    Ok((read, mime_type)) => {
+                        trace!("Clipboard changed, got data of the {} MIME type", mime_type);
+                        read
+                    }
+                    Err(Error::NoMimeType) => {
+                        trace!("Clipboard changed, but no suitable MIME type is offered");
+                        continue;
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+
+                child.env("CLIPBOARD_STATE", "data").stdin(read);
+            }
+            None => {
+                trace!("Clipboard cleared");
+                child.env("CLIPBOARD_STATE", "nil").stdin(Stdio::null());
+            }
+        }
+
+        let status = child.status().context("Couldn't run the command")?;
+        trace!("Command exited with {}", status);
+    }
+
+    Ok(())
+}
+
+fn print_selection(name: &str, mime_types: Option<Vec<String>>) {
+    match mime_types {
+        Some(mime_types) => println!("    {name}: {}", mime_types.join(", ")),
+        None => println!("    {name}: empty"),
+    }
+}
+
 fn main() -> Result<(), anyhow::Error> {
     // Parse command-line options.
     let options = Options::parse();
@@ -39,9 +89,34 @@ fn main() -> Result<(), anyhow::Error> {
         .init()
         .unwrap();
 
+    let protocol = Protocol::from_env()?;
+
+    // If listing seats is requested, do just that.
+    if options.list_seats {
+        for seat in list_seats(protocol)? {
+            println!("{}", seat.name.as_deref().unwrap_or("<unnamed>"));
+            if !seat.has_device {
+                println!("    no data-control device");
+            }
+            print_selection("selection", seat.selection);
+            match seat.primary_selection {
+                PrimarySelection::Unsupported => println!("    primary selection: unsupported"),
+                PrimarySelection::Empty => print_selection("primary selection", None),
+                PrimarySelection::Offered(mime_types) => {
+                    print_selection("primary selection", Some(mime_types))
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    let mut paste_options = wl_clipboard_rs::paste::Options::new();
+    paste_options.protocol(protocol);
+
     // If listing types is requested, do just that.
     if options.list_types {
-        let mime_types = get_mime_types(primary, seat)?;
+        let mime_types = paste_options.get_mime_types_ordered(primary, seat)?;
 
         for mime_type in mime_types.iter() {
             println!("{}", mime_type);
@@ -59,9 +134,17 @@ fn main() -> Result<(), anyhow::Error> {
         None
     };
 
+    let preferences: Vec<&str> = match options.mime_type {
+        Some(ref mime_type) if mime_type.contains([',', '*']) => {
+            mime_type.split(',').map(str::trim).collect()
+        }
+        _ => Vec::new(),
+    };
+
     // Do some smart MIME type selection.
     let mime_type = match options.mime_type {
         Some(ref mime_type) if mime_type == "text" => MimeType::Text,
+        Some(_) if !preferences.is_empty() => MimeType::Preferences(&preferences),
         Some(ref mime_type) => MimeType::Specific(mime_type),
         None => {
             let inferred: Option<&str> = inferred.as_ref().map(Mime::as_ref);
@@ -76,12 +159,29 @@ fn main() -> Result<(), anyhow::Error> {
         }
     };
 
-    let (mut read, mime_type) = get_contents(primary, seat, mime_type)?;
+    // In the watch mode, run the command on every change.
+    if let Some(command) = options.watch {
+        return watch_clipboard(paste_options, primary, seat, mime_type, &command);
+    }
+
+    if let Some(timeout) = options.timeout {
+        paste_options.timeout(timeout);
+    }
+    if let Some(max_size) = options.max_size {
+        paste_options.max_size(max_size);
+    }
+
+    // If saving is requested, save the contents in all MIME types.
+    if let Some(path) = options.save {
+        let snapshot = Snapshot::take(paste_options, primary, seat)?;
+        snapshot.save(&path).with_context(|| {
+            format!("Couldn't save the clipboard contents to {}", path.display())
+        })?;
+        return Ok(());
+    }
 
     // Read the contents.
-    let mut contents = vec![];
-    read.read_to_end(&mut contents)
-        .context("Couldn't read clipboard contents")?;
+    let (mut contents, mime_type) = paste_options.read_contents(primary, seat, mime_type)?;
 
     // Append a newline if needed.
     let last_character_is_newline = contents.last().map(|&c| c == b'\n').unwrap_or(false);
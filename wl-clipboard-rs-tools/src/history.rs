@@ -0,0 +1,752 @@
+//! Clipboard history: recording the clipboard contents and bringing them back.
+//!
+//! The history consists of three parts:
+//!
+//! - a [`Store`], which keeps a bounded number of entries on disk, each entry being a
+//!   [`Snapshot`] of the clipboard contents,
+//! - the daemon, started with [`serve()`], which records every new selection into the store and
+//!   answers requests on a Unix socket,
+//! - a [`Client`] for sending requests to the daemon.
+//!
+//! # Protocol
+//!
+//! The client connects to the daemon's Unix socket, sends a single request line terminated by
+//! `\n`, and reads the response, after which the connection is closed. The requests are:
+//!
+//! | Request             | Response payload                                             |
+//! |---------------------|--------------------------------------------------------------|
+//! | `LIST`              | One line per entry, newest first: `ID\tSIZE\tTYPE TYPE...`   |
+//! | `GET ID MIME/TYPE`  | The contents of the entry in the MIME type                   |
+//! | `SELECT ID`         | None, copies the entry back to the clipboard                 |
+//! | `DELETE ID`         | None, deletes the entry                                      |
+//! | `CLEAR`             | None, deletes all entries                                    |
+//!
+//! The response starts with either `OK LENGTH\n` followed by `LENGTH` bytes of the payload, or
+//! with `ERR MESSAGE\n` if the request failed.
+
+use std::ffi::OsString;
+use std::fs::{self, DirBuilder, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use std::{env, thread};
+
+use log::{trace, warn};
+use rustix::event::{poll, PollFd, PollFlags};
+use rustix::io::Errno;
+use wl_clipboard_rs::copy::{self, CopyHandle, MimeSource};
+use wl_clipboard_rs::paste;
+use wl_clipboard_rs::snapshot::{self, Snapshot};
+use wl_clipboard_rs::utils::Protocol;
+
+/// MIME type offered along with re-selected entries, to avoid recording them again.
+const MARKER_MIME_TYPE: &str = "application/x-wl-clipboard-rs-history";
+
+/// Extension of the entry files in the store.
+const ENTRY_EXTENSION: &str = "snapshot";
+
+/// Name of the file keeping the identifier of the next entry in the store.
+const NEXT_ID_FILE: &str = "next-id";
+
+/// Maximum length of a request line.
+const MAX_REQUEST_LEN: u64 = 4096;
+
+/// Time limit for reading a request from a client and for writing the response.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Entry of the clipboard history.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Entry {
+    /// Identifier of the entry, increasing with every recorded entry.
+    pub id: u64,
+    /// Total size of the contents in all MIME types in bytes.
+    pub size: u64,
+    /// MIME types of the contents, sorted.
+    pub mime_types: Vec<String>,
+}
+
+/// On-disk store of the clipboard history.
+///
+/// Every entry is kept in a separate file in the [snapshot format](wl_clipboard_rs::snapshot). The
+/// store keeps at most the given number of entries, removing the oldest ones. The identifiers are
+/// never reused, even after the entries are deleted.
+///
+/// The history can contain passwords and other secrets, so the store directory and files are
+/// created accessible only by the current user.
+pub struct Store {
+    dir: PathBuf,
+    max_entries: usize,
+    // Oldest first.
+    entries: Vec<Entry>,
+    next_id: u64,
+}
+
+/// Options and flags that are used to customize the history daemon.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Options {
+    /// The clipboard to record.
+    clipboard: paste::ClipboardType,
+
+    /// The seat to record, `None` means an unspecified seat.
+    seat: Option<String>,
+
+    /// Data-control protocol to use.
+    protocol: Protocol,
+
+    /// Wayland display to connect to, `None` means the one from the environment.
+    display: Option<OsString>,
+
+    /// Options for reading the clipboard contents.
+    paste: paste::Options,
+}
+
+/// Client of the history daemon.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Client {
+    socket_path: PathBuf,
+}
+
+/// Errors that can occur in the clipboard history.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Couldn't access the history store")]
+    Store(#[source] io::Error),
+
+    #[error("Couldn't read history entry {id}")]
+    Entry {
+        id: u64,
+        #[source]
+        source: snapshot::Error,
+    },
+
+    #[error("History entry {0} not found")]
+    EntryNotFound(u64),
+
+    #[error("History entry {0} has no contents of the requested MIME type")]
+    NoMimeType(u64),
+
+    #[error("Couldn't watch the clipboard")]
+    Paste(#[source] paste::Error),
+
+    #[error("Couldn't copy a history entry")]
+    Copy(#[source] copy::Error),
+
+    #[error("Couldn't communicate with the history daemon")]
+    Socket(#[source] io::Error),
+
+    #[error("Invalid message from the history daemon")]
+    Protocol,
+
+    #[error("The history daemon reported an error: {0}")]
+    Daemon(String),
+}
+
+/// Returns the default path of the history daemon socket.
+///
+/// The socket is placed in `$XDG_RUNTIME_DIR`. Returns `None` if it's not set.
+pub fn default_socket_path() -> Option<PathBuf> {
+    let dir = PathBuf::from(env::var_os("XDG_RUNTIME_DIR")?);
+    Some(dir.join("wl-clip-history.sock"))
+}
+
+/// Returns the default directory of the history store.
+///
+/// The store is placed in `$XDG_DATA_HOME`, or in `$HOME/.local/share` if it's not set. Returns
+/// `None` if neither is set.
+pub fn default_store_dir() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(dir.join("wl-clip-history"))
+}
+
+impl Entry {
+    fn new(id: u64, snapshot: &Snapshot) -> Self {
+        let mut mime_types = snapshot.contents.keys().cloned().collect::<Vec<_>>();
+        mime_types.sort_unstable();
+
+        Self {
+            id,
+            size: snapshot
+                .contents
+                .values()
+                .map(|data| data.len() as u64)
+                .sum(),
+            mime_types,
+        }
+    }
+}
+
+impl Store {
+    /// Opens the store in the given directory, creating the directory if needed.
+    ///
+    /// The store keeps at most `max_entries` entries. Entries that can't be read are skipped.
+    pub fn open(dir: impl Into<PathBuf>, max_entries: usize) -> Result<Self, Error> {
+        let dir = dir.into();
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .map_err(Error::Store)?;
+
+        let mut entries = Vec::new();
+        for file in fs::read_dir(&dir).map_err(Error::Store)? {
+            let path = file.map_err(Error::Store)?.path();
+            if path.extension().is_none_or(|ext| ext != ENTRY_EXTENSION) {
+                continue;
+            }
+
+            let Some(id) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse().ok())
+            else {
+                continue;
+            };
+
+            // Don't let a single corrupt entry take the whole history down.
+            match Snapshot::load(&path) {
+                Ok(snapshot) => entries.push(Entry::new(id, &snapshot)),
+                Err(err) => warn!("Skipping history entry {}: {}", path.display(), err),
+            }
+        }
+
+        entries.sort_unstable_by_key(|entry| entry.id);
+
+        let next_id = match fs::read_to_string(dir.join(NEXT_ID_FILE)) {
+            Ok(next_id) => next_id.trim().parse().unwrap_or_else(|_| {
+                warn!("Ignoring the invalid next history entry identifier");
+                0
+            }),
+            Err(err) if err.kind() == ErrorKind::NotFound => 0,
+            Err(err) => return Err(Error::Store(err)),
+        };
+        // The entries may have been written by a daemon that didn't keep the counter.
+        let next_id = entries
+            .last()
+            .map_or(next_id, |entry| next_id.max(entry.id + 1));
+
+        let mut store = Self {
+            dir,
+            max_entries,
+            entries,
+            next_id,
+        };
+        store.trim()?;
+        Ok(store)
+    }
+
+    /// Returns the entries, oldest first.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Adds an entry, returning its identifier.
+    ///
+    /// If the snapshot is the same as the newest entry, no entry is added and the identifier of
+    /// the newest entry is returned.
+    pub fn add(&mut self, snapshot: &Snapshot) -> Result<u64, Error> {
+        if let Some(newest) = self.entries.last() {
+            if newest == &Entry::new(newest.id, snapshot) && &self.get(newest.id)? == snapshot {
+                return Ok(newest.id);
+            }
+        }
+
+        let id = self.next_id;
+
+        write_private(&self.path(id), |file| snapshot.write_to(file)).map_err(Error::Store)?;
+
+        self.entries.push(Entry::new(id, snapshot));
+        self.next_id = id + 1;
+        self.save_next_id()?;
+        self.trim()?;
+        Ok(id)
+    }
+
+    /// Reads the contents of an entry.
+    pub fn get(&self, id: u64) -> Result<Snapshot, Error> {
+        self.find(id)?;
+        Snapshot::load(self.path(id)).map_err(|source| Error::Entry { id, source })
+    }
+
+    /// Deletes an entry.
+    pub fn delete(&mut self, id: u64) -> Result<(), Error> {
+        let index = self.find(id)?;
+        fs::remove_file(self.path(id)).map_err(Error::Store)?;
+        self.entries.remove(index);
+        Ok(())
+    }
+
+    /// Deletes all entries.
+    pub fn clear(&mut self) -> Result<(), Error> {
+        while let Some(entry) = self.entries.first() {
+            self.delete(entry.id)?;
+        }
+
+        Ok(())
+    }
+
+    fn find(&self, id: u64) -> Result<usize, Error> {
+        self.entries
+            .binary_search_by_key(&id, |entry| entry.id)
+            .map_err(|_| Error::EntryNotFound(id))
+    }
+
+    fn path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{id}.{ENTRY_EXTENSION}"))
+    }
+
+    fn save_next_id(&self) -> Result<(), Error> {
+        write_private(&self.dir.join(NEXT_ID_FILE), |mut file| {
+            write!(file, "{}", self.next_id)?;
+            file.flush()
+        })
+        .map_err(Error::Store)
+    }
+
+    // Removes the oldest entries over the limit.
+    fn trim(&mut self) -> Result<(), Error> {
+        while self.entries.len() > self.max_entries {
+            self.delete(self.entries[0].id)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Writes the file at `path`, readable only by the current user.
+//
+// The contents go to a temporary file first, which is then moved in place, so that a partially
+// written file is never picked up.
+fn write_private(
+    path: &Path,
+    write: impl FnOnce(BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut temp_path = path.to_owned().into_os_string();
+    temp_path.push(".tmp");
+
+    // A leftover from an interrupted write may have other permissions.
+    match fs::remove_file(&temp_path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+        _ => (),
+    }
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&temp_path)?;
+    write(BufWriter::new(file))?;
+    fs::rename(&temp_path, path)
+}
+
+impl Options {
+    /// Creates a blank new set of options ready for configuration.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the clipboard to record.
+    #[inline]
+    pub fn clipboard(&mut self, clipboard: paste::ClipboardType) -> &mut Self {
+        self.clipboard = clipboard;
+        self
+    }
+
+    /// Sets the seat to record.
+    ///
+    /// By default an unspecified seat is used (it depends on the order returned by the
+    /// compositor). This is perfectly fine when only a single seat is present, so for most
+    /// configurations.
+    #[inline]
+    pub fn seat(&mut self, seat: String) -> &mut Self {
+        self.seat = Some(seat);
+        self
+    }
+
+    /// Sets the data-control protocol for recording and bringing back the entries.
+    #[inline]
+    pub fn protocol(&mut self, protocol: Protocol) -> &mut Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Sets the Wayland display for recording and bringing back the entries.
+    ///
+    /// See [`paste::Options::display()`] for the accepted values.
+    #[inline]
+    pub fn display(&mut self, display: impl Into<OsString>) -> &mut Self {
+        self.display = Some(display.into());
+        self
+    }
+
+    /// Sets the options for reading the clipboard contents.
+    ///
+    /// The options limit the size and the MIME types of the recorded contents. Selections that
+    /// fail to be read within the limits are not recorded. Their protocol and display are
+    /// overridden by [`protocol()`](Self::protocol) and [`display()`](Self::display).
+    #[inline]
+    pub fn paste_options(&mut self, options: paste::Options) -> &mut Self {
+        self.paste = options;
+        self
+    }
+
+    /// Invokes the serve operation. See `serve()`.
+    #[inline]
+    pub fn serve(self, store: Store, listener: UnixListener) -> Result<(), Error> {
+        serve(self, store, listener)
+    }
+}
+
+/// Runs the history daemon.
+///
+/// Records every new selection into `store`, and answers the requests of [`Client`]s on
+/// `listener`. Blocks until watching the clipboard fails, e.g. because the seat goes away, and
+/// stops answering the requests before returning. Entries that fail to be stored are logged and
+/// skipped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::os::unix::net::UnixListener;
+/// use wl_clipboard_rs_tools::history::{default_socket_path, default_store_dir, Options, Store};
+///
+/// let store = Store::open(default_store_dir().unwrap(), 100)?;
+/// let listener = UnixListener::bind(default_socket_path().unwrap())?;
+/// Options::new().serve(store, listener)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn serve(mut options: Options, store: Store, listener: UnixListener) -> Result<(), Error> {
+    options.paste.protocol(options.protocol);
+    if let Some(display) = &options.display {
+        options.paste.display(display.clone());
+    }
+
+    let seat = options
+        .seat
+        .as_deref()
+        .map_or(paste::Seat::Unspecified, paste::Seat::Specific);
+    let watcher = options
+        .paste
+        .clone()
+        .watch(options.clipboard, seat)
+        .map_err(Error::Paste)?;
+
+    let store = Mutex::new(store);
+
+    // Closing the write end signals the listening thread to stop.
+    let (stop_write, stop_read) = UnixStream::pair().map_err(Error::Socket)?;
+
+    thread::scope(|scope| {
+        scope.spawn(|| listen(&listener, &stop_read, &store, &options));
+
+        let result = record(watcher, &store, &options);
+        drop(stop_write);
+        result
+    })
+}
+
+// Records every new selection into the store until watching the clipboard fails.
+//
+// Failing to store an entry is only logged.
+fn record(watcher: paste::Watcher, store: &Mutex<Store>, options: &Options) -> Result<(), Error> {
+    for offer in watcher {
+        let Some(offer) = offer.map_err(Error::Paste)? else {
+            continue;
+        };
+
+        if offer.mime_types().iter().any(|x| x == MARKER_MIME_TYPE) {
+            trace!("The clipboard holds a history entry");
+            continue;
+        }
+
+        let contents = match offer.receive_all(options.paste.clone()) {
+            Ok(contents) if !contents.is_empty() => contents,
+            Ok(_) => continue,
+            Err(err) => {
+                trace!("Couldn't read the clipboard contents: {}", err);
+                continue;
+            }
+        };
+
+        // Don't stop recording over a passing problem such as a full disk.
+        match store.lock().unwrap().add(&Snapshot { contents }) {
+            Ok(id) => trace!("Recorded history entry {}", id),
+            Err(err) => warn!("Couldn't record a history entry: {}", error_message(&err)),
+        }
+    }
+
+    Ok(())
+}
+
+// Answers the client requests until `stop` becomes readable.
+//
+// Stops serving the last selected entry before returning.
+pub(crate) fn listen(
+    listener: &UnixListener,
+    stop: &UnixStream,
+    store: &Mutex<Store>,
+    options: &Options,
+) {
+    // The copy of the last selected entry.
+    let mut selection = None;
+
+    loop {
+        let mut fds = [
+            PollFd::new(listener, PollFlags::IN),
+            PollFd::new(stop, PollFlags::IN),
+        ];
+        match poll(&mut fds, -1) {
+            Ok(_) => (),
+            Err(Errno::INTR) => continue,
+            Err(err) => {
+                warn!("Couldn't wait for history clients: {}", err);
+                break;
+            }
+        }
+
+        if !fds[1].revents().is_empty() {
+            break;
+        }
+
+        let result = listener
+            .accept()
+            .map_err(Error::Socket)
+            .and_then(|(stream, _)| handle_client(stream, store, &mut selection, options));
+        if let Err(err) = result {
+            trace!("Couldn't handle a history client: {}", err);
+        }
+    }
+
+    stop_serving(selection);
+}
+
+// Stops serving the copy of a selected entry, if any.
+fn stop_serving(selection: Option<CopyHandle>) {
+    if let Some(handle) = selection {
+        handle.cancel();
+        if let Err(err) = handle.join() {
+            trace!("Serving a history entry failed: {}", err);
+        }
+    }
+}
+
+// Answers a single client request.
+fn handle_client(
+    stream: UnixStream,
+    store: &Mutex<Store>,
+    selection: &mut Option<CopyHandle>,
+    options: &Options,
+) -> Result<(), Error> {
+    stream
+        .set_read_timeout(Some(CLIENT_TIMEOUT))
+        .map_err(Error::Socket)?;
+    stream
+        .set_write_timeout(Some(CLIENT_TIMEOUT))
+        .map_err(Error::Socket)?;
+
+    let mut request = String::new();
+    BufReader::new(&stream)
+        .take(MAX_REQUEST_LEN)
+        .read_line(&mut request)
+        .map_err(Error::Socket)?;
+    let request = request.trim_end_matches('\n');
+    trace!("History request: {}", request);
+
+    let response = handle_request(request, store, selection, options);
+
+    let mut stream = &stream;
+    match response {
+        Ok(payload) => {
+            writeln!(stream, "OK {}", payload.len()).map_err(Error::Socket)?;
+            stream.write_all(&payload).map_err(Error::Socket)
+        }
+        Err(err) => writeln!(stream, "ERR {}", error_message(&err)).map_err(Error::Socket),
+    }
+}
+
+fn handle_request(
+    request: &str,
+    store: &Mutex<Store>,
+    selection: &mut Option<CopyHandle>,
+    options: &Options,
+) -> Result<Vec<u8>, Error> {
+    let mut args = request.split(' ');
+    let command = args.next().unwrap_or_default();
+    let mut id = || {
+        args.next()
+            .and_then(|id| id.parse::<u64>().ok())
+            .ok_or(Error::Protocol)
+    };
+
+    match command {
+        "LIST" => {
+            let mut payload = Vec::new();
+            for entry in store.lock().unwrap().entries().iter().rev() {
+                let mime_types = entry.mime_types.join(" ");
+                writeln!(payload, "{}\t{}\t{}", entry.id, entry.size, mime_types).unwrap();
+            }
+            Ok(payload)
+        }
+        "GET" => {
+            let id = id()?;
+            let mime_type = args.next().ok_or(Error::Protocol)?;
+            let mut snapshot = store.lock().unwrap().get(id)?;
+            snapshot
+                .contents
+                .remove(mime_type)
+                .ok_or(Error::NoMimeType(id))
+        }
+        "SELECT" => {
+            let id = id()?;
+            let snapshot = store.lock().unwrap().get(id)?;
+
+            let mut sources = snapshot.into_sources();
+            sources.push(MimeSource {
+                source: copy::Source::Bytes(Box::default()),
+                mime_type: copy::MimeType::Specific(MARKER_MIME_TYPE.to_owned()),
+            });
+
+            let mut copy_options = copy::Options::new();
+            copy_options
+                .clipboard(match options.clipboard {
+                    paste::ClipboardType::Regular => copy::ClipboardType::Regular,
+                    paste::ClipboardType::Primary => copy::ClipboardType::Primary,
+                })
+                .seat(
+                    options
+                        .seat
+                        .clone()
+                        .map_or(copy::Seat::All, copy::Seat::Specific),
+                )
+                .omit_additional_text_mime_types(true)
+                .protocol(options.protocol);
+            if let Some(display) = &options.display {
+                copy_options.display(display.clone());
+            }
+
+            // The copy is served in the background until the clipboard changes or another entry is
+            // selected.
+            stop_serving(selection.take());
+            *selection = Some(copy_options.copy_multi(sources).map_err(Error::Copy)?);
+            Ok(Vec::new())
+        }
+        "DELETE" => {
+            store.lock().unwrap().delete(id()?)?;
+            Ok(Vec::new())
+        }
+        "CLEAR" => {
+            store.lock().unwrap().clear()?;
+            Ok(Vec::new())
+        }
+        _ => Err(Error::Protocol),
+    }
+}
+
+// Formats the error along with its sources on a single line.
+fn error_message(err: &dyn std::error::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+    message.replace('\n', " ")
+}
+
+impl Client {
+    /// Creates a client of the daemon listening on the given socket.
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+        }
+    }
+
+    /// Lists the history entries, newest first.
+    pub fn list(&self) -> Result<Vec<Entry>, Error> {
+        let payload = self.request("LIST")?;
+        let payload = String::from_utf8(payload).map_err(|_| Error::Protocol)?;
+
+        payload
+            .lines()
+            .map(|line| {
+                let mut fields = line.split('\t');
+                let mut next = || fields.next().ok_or(Error::Protocol);
+                let id = next()?.parse().map_err(|_| Error::Protocol)?;
+                let size = next()?.parse().map_err(|_| Error::Protocol)?;
+                let mime_types = next()?
+                    .split(' ')
+                    .filter(|mime_type| !mime_type.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+                Ok(Entry {
+                    id,
+                    size,
+                    mime_types,
+                })
+            })
+            .collect()
+    }
+
+    /// Retrieves the contents of a history entry in the given MIME type.
+    pub fn get(&self, id: u64, mime_type: &str) -> Result<Vec<u8>, Error> {
+        if mime_type.is_empty() || mime_type.contains([' ', '\n']) {
+            return Err(Error::NoMimeType(id));
+        }
+
+        self.request(&format!("GET {id} {mime_type}"))
+    }
+
+    /// Copies a history entry back to the clipboard.
+    pub fn select(&self, id: u64) -> Result<(), Error> {
+        self.request(&format!("SELECT {id}")).map(drop)
+    }
+
+    /// Deletes a history entry.
+    pub fn delete(&self, id: u64) -> Result<(), Error> {
+        self.request(&format!("DELETE {id}")).map(drop)
+    }
+
+    /// Deletes all history entries.
+    pub fn clear(&self) -> Result<(), Error> {
+        self.request("CLEAR").map(drop)
+    }
+
+    // Sends the request and returns the response payload.
+    fn request(&self, request: &str) -> Result<Vec<u8>, Error> {
+        let mut stream = UnixStream::connect(&self.socket_path).map_err(Error::Socket)?;
+        writeln!(stream, "{request}").map_err(Error::Socket)?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader.read_line(&mut status).map_err(Error::Socket)?;
+        let status = status.strip_suffix('\n').ok_or(Error::Protocol)?;
+
+        if let Some(message) = status.strip_prefix("ERR ") {
+            return Err(Error::Daemon(message.to_owned()));
+        }
+
+        let len = status
+            .strip_prefix("OK ")
+            .and_then(|len| len.parse::<u64>().ok())
+            .ok_or(Error::Protocol)?;
+
+        let mut payload = Vec::new();
+        reader
+            .take(len)
+            .read_to_end(&mut payload)
+            .map_err(Error::Socket)?;
+        if payload.len() as u64 != len {
+            return Err(Error::Protocol);
+        }
+
+        Ok(payload)
+    }
+}
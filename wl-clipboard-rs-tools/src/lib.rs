@@ -1,2 +1,11 @@
+#[cfg(test)]
+mod tests;
+
+pub mod history;
+pub mod parse;
+pub mod wl_clip_history;
+pub mod wl_clip_historyd;
+pub mod wl_clip_persist;
+pub mod wl_clip_probe;
 pub mod wl_copy;
 pub mod wl_paste;
@@ -0,0 +1,7 @@
+use std::time::Duration;
+
+/// Parses a possibly fractional number of seconds.
+pub fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds = value.parse::<f64>().map_err(|err| err.to_string())?;
+    Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string())
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
+use wl_clipboard_rs::snapshot::Snapshot;
+use wl_clipboard_rs::{copy, paste};
+
+use crate::history::*;
+use crate::tests::state::*;
+use crate::tests::TestServer;
+
+fn snapshot(data: &[u8]) -> Snapshot {
+    Snapshot {
+        contents: HashMap::from([("text/plain".into(), data.to_vec())]),
+    }
+}
+
+#[test]
+fn store_test() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut store = Store::open(dir.path(), 2).unwrap();
+    assert!(store.entries().is_empty());
+
+    assert_eq!(store.add(&snapshot(b"a")).unwrap(), 0);
+    assert_eq!(store.add(&snapshot(b"bc")).unwrap(), 1);
+    // The same contents as the newest entry aren't recorded again.
+    assert_eq!(store.add(&snapshot(b"bc")).unwrap(), 1);
+    assert_eq!(store.add(&snapshot(b"def")).unwrap(), 2);
+
+    // The oldest entry is removed over the limit.
+    let ids = store.entries().iter().map(|e| e.id).collect::<Vec<_>>();
+    assert_eq!(ids, [1, 2]);
+    assert!(matches!(store.get(0), Err(Error::EntryNotFound(0))));
+    assert_eq!(store.get(2).unwrap(), snapshot(b"def"));
+
+    // The entries persist.
+    let mut store = Store::open(dir.path(), 1).unwrap();
+    assert_eq!(
+        store.entries(),
+        [Entry {
+            id: 2,
+            size: 3,
+            mime_types: vec!["text/plain".into()],
+        }]
+    );
+
+    // The identifiers of the deleted entries aren't reused.
+    store.delete(2).unwrap();
+    assert!(store.entries().is_empty());
+    assert_eq!(store.add(&snapshot(b"g")).unwrap(), 3);
+
+    store.clear().unwrap();
+    let mut store = Store::open(dir.path(), 1).unwrap();
+    assert!(store.entries().is_empty());
+    assert_eq!(store.add(&snapshot(b"h")).unwrap(), 4);
+}
+
+#[test]
+fn store_permissions_test() {
+    let dir = tempfile::tempdir().unwrap();
+    let store_dir = dir.path().join("history");
+
+    let mut store = Store::open(&store_dir, 10).unwrap();
+    store.add(&snapshot(b"hunter2")).unwrap();
+
+    let mode = |name: &str| {
+        let metadata = fs::metadata(store_dir.join(name)).unwrap();
+        metadata.permissions().mode() & 0o777
+    };
+    assert_eq!(mode(""), 0o700);
+    assert_eq!(mode("0.snapshot"), 0o600);
+    assert_eq!(mode("next-id"), 0o600);
+}
+
+#[test]
+fn store_corrupt_entry_test() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut store = Store::open(dir.path(), 10).unwrap();
+    assert_eq!(store.add(&snapshot(b"a")).unwrap(), 0);
+    assert_eq!(store.add(&snapshot(b"b")).unwrap(), 1);
+
+    fs::write(dir.path().join("0.snapshot"), b"garbage").unwrap();
+
+    // The corrupt entry is skipped, the rest of the history is still there.
+    let store = Store::open(dir.path(), 10).unwrap();
+    let ids = store.entries().iter().map(|e| e.id).collect::<Vec<_>>();
+    assert_eq!(ids, [1]);
+    assert_eq!(store.get(1).unwrap(), snapshot(b"b"));
+}
+
+#[test]
+fn history_listen_test() {
+    let store_dir = tempfile::tempdir().unwrap();
+    let socket_dir = tempfile::tempdir().unwrap();
+    let socket_path = socket_dir.path().join("history.sock");
+
+    let mut store = Store::open(store_dir.path(), 10).unwrap();
+    store.add(&snapshot(b"first")).unwrap();
+    store.add(&snapshot(b"second")).unwrap();
+    let store = Mutex::new(store);
+
+    let listener = UnixListener::bind(&socket_path).unwrap();
+    let (stop_write, stop_read) = UnixStream::pair().unwrap();
+    let options = Options::new();
+
+    thread::scope(|scope| {
+        let listener_thread = scope.spawn(|| listen(&listener, &stop_read, &store, &options));
+
+        let client = Client::new(&socket_path);
+        assert_eq!(
+            client.list().unwrap(),
+            [
+                Entry {
+                    id: 1,
+                    size: 6,
+                    mime_types: vec!["text/plain".into()],
+                },
+                Entry {
+                    id: 0,
+                    size: 5,
+                    mime_types: vec!["text/plain".into()],
+                },
+            ]
+        );
+
+        assert_eq!(client.get(0, "text/plain").unwrap(), b"first");
+        assert!(matches!(client.get(0, "other"), Err(Error::Daemon(_))));
+        assert!(matches!(client.get(5, "text/plain"), Err(Error::Daemon(_))));
+
+        client.delete(1).unwrap();
+        assert_eq!(client.list().unwrap().len(), 1);
+
+        client.clear().unwrap();
+        assert!(client.list().unwrap().is_empty());
+
+        // The listening stops once signalled.
+        drop(stop_write);
+        listener_thread.join().unwrap();
+    });
+}
+
+#[test]
+fn history_daemon_test() {
+    let server = TestServer::new();
+    server
+        .display
+        .handle()
+        .create_global::<State, ZwlrDataControlManagerV1, ()>(2, ());
+
+    let state = State {
+        seats: HashMap::from([(
+            "seat0".into(),
+            SeatInfo {
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    state.create_seats(&server);
+
+    let socket_name = server.socket_name().to_owned();
+    server.run(state);
+
+    let store_dir = tempfile::tempdir().unwrap();
+    let socket_dir = tempfile::tempdir().unwrap();
+    let socket_path = socket_dir.path().join("history.sock");
+
+    let store = Store::open(store_dir.path(), 10).unwrap();
+    let listener = UnixListener::bind(&socket_path).unwrap();
+    let mut options = Options::new();
+    options.display(&socket_name);
+    thread::spawn(move || options.serve(store, listener));
+
+    let client = Client::new(&socket_path);
+    let wait_for_entries = |count| {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let entries = client.list().unwrap();
+            if entries.len() == count {
+                return entries;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for entries");
+            thread::sleep(Duration::from_millis(10));
+        }
+    };
+    let copy = |data: &[u8]| {
+        let mut options = copy::Options::new();
+        options.display(&socket_name);
+        options
+            .copy_multi(vec![copy::MimeSource {
+                source: copy::Source::Bytes(data.into()),
+                mime_type: copy::MimeType::Specific("test".into()),
+            }])
+            .unwrap()
+    };
+
+    // Every new selection is recorded.
+    for (count, data) in [&b"first"[..], &b"second"[..]].into_iter().enumerate() {
+        let handle = copy(data);
+        wait_for_entries(count + 1);
+        handle.cancel();
+        handle.join().unwrap();
+    }
+
+    assert_eq!(
+        client.list().unwrap(),
+        [
+            Entry {
+                id: 1,
+                size: 6,
+                mime_types: vec!["test".into()],
+            },
+            Entry {
+                id: 0,
+                size: 5,
+                mime_types: vec!["test".into()],
+            },
+        ]
+    );
+    assert_eq!(client.get(0, "test").unwrap(), b"first");
+
+    // Selecting an entry copies it back.
+    client.select(0).unwrap();
+    let mut paste_options = paste::Options::new();
+    paste_options.display(&socket_name);
+    let (mut read, mime_type) = paste_options
+        .get_contents(
+            paste::ClipboardType::Regular,
+            paste::Seat::Unspecified,
+            paste::MimeType::Specific("test"),
+        )
+        .unwrap();
+    let mut contents = vec![];
+    read.read_to_end(&mut contents).unwrap();
+    assert_eq!(mime_type, "test");
+    assert_eq!(contents, b"first");
+
+    // The selected entry isn't recorded again, unlike the selection after it.
+    let handle = copy(b"the third");
+    let entries = wait_for_entries(3);
+    assert_eq!(entries[0].id, 2);
+    assert_eq!(client.get(2, "test").unwrap(), b"the third");
+    handle.cancel();
+    handle.join().unwrap();
+}
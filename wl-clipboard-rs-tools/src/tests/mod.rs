@@ -0,0 +1,11 @@
+// The test compositor of the library.
+#[allow(dead_code)]
+#[path = "../../../src/tests/server.rs"]
+mod server;
+#[allow(dead_code)]
+#[path = "../../../src/tests/state.rs"]
+mod state;
+
+mod history;
+
+pub use server::TestServer;
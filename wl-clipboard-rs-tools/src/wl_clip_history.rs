@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(
+    name = "wl-clip-history",
+    version,
+    about = "Browse the Wayland clipboard history recorded by wl-clip-historyd."
+)]
+pub struct Options {
+    /// Path of the wl-clip-historyd socket
+    ///
+    /// Defaults to $XDG_RUNTIME_DIR/wl-clip-history.sock.
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List the entries, newest first
+    ///
+    /// Every line holds the entry ID, the total size in bytes and the MIME types, separated by
+    /// tabs.
+    List,
+
+    /// Write the contents of an entry to stdout
+    Get {
+        /// ID of the entry
+        id: u64,
+
+        /// MIME type to write
+        ///
+        /// Defaults to a text type if the entry has one, otherwise to its first MIME type.
+        #[arg(value_name = "MIME/TYPE")]
+        mime_type: Option<String>,
+    },
+
+    /// Copy an entry back to the clipboard
+    Select {
+        /// ID of the entry
+        id: u64,
+    },
+
+    /// Delete an entry
+    Delete {
+        /// ID of the entry
+        id: u64,
+    },
+
+    /// Delete all entries
+    Clear,
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+
+use crate::parse::parse_seconds;
+
+#[derive(Parser)]
+#[command(
+    name = "wl-clip-historyd",
+    version,
+    about = "Record the Wayland clipboard history and serve it to wl-clip-history."
+)]
+pub struct Options {
+    /// Directory to keep the history in
+    ///
+    /// Defaults to $XDG_DATA_HOME/wl-clip-history.
+    #[arg(long, value_name = "DIR")]
+    pub store: Option<PathBuf>,
+
+    /// Path of the socket to listen on
+    ///
+    /// Defaults to $XDG_RUNTIME_DIR/wl-clip-history.sock.
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// Keep at most the given number of entries, removing the oldest ones
+    #[arg(long, value_name = "COUNT", default_value = "100")]
+    pub max_entries: usize,
+
+    /// Don't record the clipboard if any MIME type of its contents is larger than the given
+    /// number of bytes
+    #[arg(long, value_name = "BYTES")]
+    pub max_size: Option<u64>,
+
+    /// Don't record the clipboard if reading its contents takes longer than the given number of
+    /// seconds
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, default_value = "5")]
+    pub timeout: Duration,
+
+    /// Use the "primary" clipboard
+    ///
+    /// Working with the "primary" clipboard requires the compositor to support the data-control
+    /// protocol of version 2 or above.
+    #[arg(long, short)]
+    pub primary: bool,
+
+    /// Pick the seat to work with
+    ///
+    /// By default the seat used is unspecified (it depends on the order returned by the
+    /// compositor). This is perfectly fine when only a single seat is present, so for most
+    /// configurations.
+    #[arg(long, short)]
+    pub seat: Option<String>,
+
+    /// Enable verbose logging
+    #[arg(long, short, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+}
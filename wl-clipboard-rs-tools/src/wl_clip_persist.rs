@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use clap::Parser;
+
+use crate::parse::parse_seconds;
+
+#[derive(Parser)]
+#[command(
+    name = "wl-clip-persist",
+    version,
+    about = "Keep the Wayland clipboard contents after the copying application exits."
+)]
+pub struct Options {
+    /// Use the "primary" clipboard
+    ///
+    /// Working with the "primary" clipboard requires the compositor to support the data-control
+    /// protocol of version 2 or above.
+    #[arg(long, short)]
+    pub primary: bool,
+
+    /// Pick the seat to work with
+    ///
+    /// By default the seat used is unspecified (it depends on the order returned by the
+    /// compositor). This is perfectly fine when only a single seat is present, so for most
+    /// configurations.
+    #[arg(long, short)]
+    pub seat: Option<String>,
+
+    /// Only keep the given MIME types
+    ///
+    /// Can be specified multiple times. By default all offered MIME types are kept.
+    #[arg(long, value_name = "MIME/TYPE")]
+    pub allow_type: Vec<String>,
+
+    /// Never keep the given MIME types
+    ///
+    /// Can be specified multiple times.
+    #[arg(long, value_name = "MIME/TYPE")]
+    pub deny_type: Vec<String>,
+
+    /// Leave the clipboard alone if any MIME type of its contents is larger than the given number
+    /// of bytes
+    #[arg(long, value_name = "BYTES")]
+    pub max_size: Option<u64>,
+
+    /// Leave the clipboard alone if reading its contents takes longer than the given number of
+    /// seconds
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, default_value = "5")]
+    pub timeout: Duration,
+
+    /// Enable verbose logging
+    #[arg(long, short, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+}
@@ -0,0 +1,9 @@
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(
+    name = "wl-clip-probe",
+    version,
+    about = "Print the clipboard capabilities of the Wayland compositor, for bug reports."
+)]
+pub struct Options {}
@@ -1,4 +1,5 @@
 use std::ffi::OsString;
+use std::path::PathBuf;
 
 use clap::Parser;
 
@@ -61,6 +62,14 @@ pub struct Options {
     )]
     pub mime_type: Option<String>,
 
+    /// Copy back the clipboard contents saved with wl-paste --save
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["clear", "trim_newline", "MIME/TYPE", "TEXT TO COPY"]
+    )]
+    pub restore: Option<PathBuf>,
+
     /// Text to copy
     ///
     /// If not specified, wl-copy will use data from the standard input.
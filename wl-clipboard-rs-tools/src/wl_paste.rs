@@ -1,5 +1,11 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+
 use clap::Parser;
 
+use crate::parse::parse_seconds;
+
 #[derive(Parser)]
 #[command(
     name = "wl-paste",
@@ -8,9 +14,20 @@ use clap::Parser;
 )]
 pub struct Options {
     /// List the offered MIME types instead of pasting
+    ///
+    /// The MIME types are listed in the order they were offered.
     #[arg(long, short)]
     pub list_types: bool,
 
+    /// List the seats along with the MIME types of their selections instead of pasting
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "list_types", "primary", "seat", "no_newline", "MIME/TYPE", "watch", "save"
+        ]
+    )]
+    pub list_seats: bool,
+
     /// Use the "primary" clipboard
     ///
     /// Pasting to the "primary" clipboard requires the compositor to support the data-control
@@ -36,6 +53,9 @@ pub struct Options {
     ///
     /// As a special case, specifying "text" will look for a number of plain text types,
     /// prioritizing ones that are known to give UTF-8 text.
+    ///
+    /// Several MIME types can be given separated by commas, in the order of preference. They
+    /// can contain wildcards, like "image/png,image/*".
     #[arg(
         name = "MIME/TYPE",
         long = "type",
@@ -44,6 +64,54 @@ pub struct Options {
     )]
     pub mime_type: Option<String>,
 
+    /// Run a command each time the clipboard contents change
+    ///
+    /// The command is run with the new clipboard contents on its standard input. The
+    /// CLIPBOARD_STATE environment variable is set to "data" if the clipboard holds data, or to
+    /// "nil" if the clipboard is empty, in which case the standard input is empty too. If the
+    /// clipboard holds no data of the requested MIME type, the command is not run.
+    ///
+    /// The command and its arguments must come last.
+    #[arg(
+        long,
+        short,
+        value_name = "COMMAND",
+        num_args = 1..,
+        allow_hyphen_values = true,
+        conflicts_with_all = ["list_types", "no_newline"]
+    )]
+    pub watch: Option<Vec<OsString>>,
+
+    /// Save the clipboard contents in all offered MIME types to a file
+    ///
+    /// The saved contents can be copied back to the clipboard with wl-copy --restore.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["list_types", "no_newline", "MIME/TYPE", "watch"]
+    )]
+    pub save: Option<PathBuf>,
+
+    /// Give up if pasting takes longer than the given number of seconds
+    ///
+    /// The time limit covers both the exchange with the compositor and the transfer of the
+    /// clipboard contents, which may never finish if the client that copied them misbehaves.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = parse_seconds,
+        conflicts_with_all = ["list_types", "watch"]
+    )]
+    pub timeout: Option<Duration>,
+
+    /// Give up if the clipboard contents are larger than the given number of bytes
+    #[arg(
+        long,
+        value_name = "BYTES",
+        conflicts_with_all = ["list_types", "watch"]
+    )]
+    pub max_size: Option<u64>,
+
     /// Enable verbose logging
     #[arg(long, short, action = clap::ArgAction::Count)]
     pub verbose: u8,